@@ -0,0 +1,499 @@
+use crate::*;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// How an archive location handles write operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArchiveMode {
+    /// All write operations are unsupported
+    #[default]
+    ReadOnly,
+    /// New entries are appended to the end of the archive and shadow older ones with the same name.
+    /// Removing is unsupported, because it would require a rewrite.
+    /// Appended tar archives are concatenated, other tools may need to ignore zero blocks to read them (`tar -i`).
+    Append,
+    /// Every write operation rewrites the whole archive into a temporary file,
+    /// which replaces the archive afterwards
+    Rewrite,
+}
+
+/// Format of the archive, detected by its file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+/// Source of a new entry
+enum Entry {
+    Dir,
+    /// The data got spooled into the file before, to know its size
+    File(PathBuf),
+}
+
+/// Location in a `.tar`, `.tar.gz`/`.tgz` or `.zip` file.
+/// Top-level directories are containers, the files in them are items.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    path: PathBuf,
+    format: Format,
+    mode: ArchiveMode,
+    /// Serializes the write operations of all clones
+    lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Archive {
+    /// Open the archive at the given path.
+    /// In a write mode the archive gets created if not available.
+    pub async fn new(path: &str, mode: ArchiveMode) -> Result<Self> {
+        let name = path.to_lowercase();
+        let format = if name.ends_with(".zip") {
+            Format::Zip
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Format::TarGz
+        } else if name.ends_with(".tar") {
+            Format::Tar
+        } else {
+            return Err(StowError::Unsupported);
+        };
+
+        let this = Self {
+            path: PathBuf::from(path),
+            format,
+            mode,
+            lock: Arc::new(tokio::sync::Mutex::new(())),
+        };
+
+        if tokio::fs::metadata(&this.path).await.is_err() {
+            if mode == ArchiveMode::ReadOnly {
+                return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+            }
+            this.blocking(|path, format| rewrite(path, format, |_| false, vec![]))
+                .await?;
+        }
+
+        Ok(this)
+    }
+
+    /// Run a blocking operation on the archive on the blocking thread pool
+    async fn blocking<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Path, Format) -> std::io::Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let path = self.path.clone();
+        let format = self.format;
+        Ok(tokio::task::spawn_blocking(move || f(&path, format)).await??)
+    }
+
+    /// Get the names of all entries, directories end with a slash
+    async fn names(&self) -> Result<Vec<String>> {
+        self.blocking(names).await
+    }
+
+    /// Fail if the archive can't be changed in the current mode
+    fn writable(&self, remove: bool) -> Result<()> {
+        match self.mode {
+            ArchiveMode::Rewrite => Ok(()),
+            ArchiveMode::Append if !remove => Ok(()),
+            _ => Err(StowError::Unsupported),
+        }
+    }
+
+    /// Add the entries to the archive, depending on the mode
+    async fn add(&self, entries: Vec<(String, Entry)>) -> Result<()> {
+        if self.mode == ArchiveMode::Append {
+            return self
+                .blocking(move |path, format| append(path, format, entries))
+                .await;
+        }
+
+        let names: BTreeSet<String> = entries.iter().map(|(n, _)| n.clone()).collect();
+        self.blocking(move |path, format| {
+            rewrite(path, format, move |n| names.contains(n), entries)
+        })
+        .await
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Archive {
+    async fn containers(&self) -> Result<Vec<String>> {
+        let containers: BTreeSet<String> = self
+            .names()
+            .await?
+            .iter()
+            .filter_map(|n| n.split_once('/'))
+            .map(|(c, _)| c.to_string())
+            .collect();
+
+        Ok(containers.into_iter().collect())
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        self.writable(false)?;
+        let _lock = self.lock.lock().await;
+
+        if self.containers().await?.iter().any(|c| c == container) {
+            return Ok(());
+        }
+        self.add(vec![(format!("{}/", container), Entry::Dir)])
+            .await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        self.writable(true)?;
+        let _lock = self.lock.lock().await;
+
+        let prefix = format!("{}/", util::streamline(container));
+        self.blocking(move |path, format| {
+            rewrite(path, format, move |n| n.starts_with(&prefix), vec![])
+        })
+        .await
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}/", util::streamline(container));
+
+        let items: BTreeSet<String> = self
+            .names()
+            .await?
+            .iter()
+            .filter_map(|n| n.strip_prefix(&prefix))
+            .filter(|n| !n.is_empty() && !n.ends_with('/'))
+            .map(|n| n.to_string())
+            .collect();
+
+        Ok(items.into_iter().collect())
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        self.writable(false)?;
+        let item = util::streamline_item(item)?;
+
+        if !self.containers().await?.iter().any(|c| c == container) {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+        }
+
+        // tar needs the size before the data, so the item is spooled next to the archive
+        let spool = temp_path(&self.path);
+        let mut file = tokio::fs::File::create(&spool).await?;
+        let res = tokio::io::copy(&mut reader, &mut file).await;
+        drop(file);
+
+        let res = match res {
+            Ok(_) => {
+                let _lock = self.lock.lock().await;
+                self.add(vec![(
+                    format!("{}/{}", container, item),
+                    Entry::File(spool.clone()),
+                )])
+                .await
+            }
+            Err(e) => Err(e.into()),
+        };
+
+        tokio::fs::remove_file(&spool).await?;
+        res
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let name = format!(
+            "{}/{}",
+            util::streamline(container),
+            util::streamline_item(item)?
+        );
+
+        // fail early, instead of on the first read
+        if !self.names().await?.contains(&name) {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+        }
+
+        let path = self.path.clone();
+        let format = self.format;
+        Ok(util::blocking_stream(move |writer| {
+            read(&path, format, &name, writer)
+        }))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        self.writable(true)?;
+        let name = format!(
+            "{}/{}",
+            util::streamline(container),
+            util::streamline_item(item)?
+        );
+        let _lock = self.lock.lock().await;
+
+        if !self.names().await?.contains(&name) {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+        }
+        self.blocking(move |path, format| rewrite(path, format, move |n| n == name, vec![]))
+            .await
+    }
+}
+
+/// Get a unique path for a temporary file next to the given one
+fn temp_path(path: &Path) -> PathBuf {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", nanos));
+    path.with_file_name(name)
+}
+
+/// Open a tar archive, which can consist of multiple appended archives
+fn open_tar(path: &Path, format: Format) -> std::io::Result<tar::Archive<Box<dyn Read>>> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let read: Box<dyn Read> = match format {
+        Format::TarGz => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+
+    let mut archive = tar::Archive::new(read);
+    archive.set_ignore_zeros(true);
+    Ok(archive)
+}
+
+/// Open a zip archive
+fn open_zip(path: &Path) -> std::io::Result<zip::ZipArchive<std::fs::File>> {
+    Ok(zip::ZipArchive::new(std::fs::File::open(path)?)?)
+}
+
+/// Get the names of all entries in the order of the archive, directories end with a slash
+fn names(path: &Path, format: Format) -> std::io::Result<Vec<String>> {
+    let mut names = vec![];
+
+    if format == Format::Zip {
+        let mut archive = open_zip(path)?;
+        for i in 0..archive.len() {
+            let file = archive.by_index(i)?;
+            names.push(entry_name(file.name(), file.is_dir()));
+        }
+        return Ok(names);
+    }
+
+    for entry in open_tar(path, format)?.entries()? {
+        let entry = entry?;
+        let dir = entry.header().entry_type().is_dir();
+        names.push(entry_name(&entry.path()?.to_string_lossy(), dir));
+    }
+    Ok(names)
+}
+
+/// Normalize the name of an entry, directories end with a slash
+fn entry_name(name: &str, dir: bool) -> String {
+    let name = name.trim_start_matches("./").trim_matches('/');
+    if dir {
+        format!("{}/", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Get the index of the last entry of every name, which shadows the earlier ones
+fn last_entries(names: &[String]) -> HashMap<&str, usize> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n.as_str(), i))
+        .collect()
+}
+
+/// Write the data of the entry with the given name
+fn read(path: &Path, format: Format, name: &str, writer: &mut dyn Write) -> std::io::Result<()> {
+    if format == Format::Zip {
+        let mut archive = open_zip(path)?;
+        std::io::copy(&mut archive.by_name(name)?, writer)?;
+        return Ok(());
+    }
+
+    let names = names(path, format)?;
+    let index = *last_entries(&names)
+        .get(name)
+        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+
+    let mut archive = open_tar(path, format)?;
+    let mut entry = archive
+        .entries()?
+        .nth(index)
+        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof))??;
+    std::io::copy(&mut entry, writer)?;
+    Ok(())
+}
+
+/// Append the entries to the end of the archive
+fn append(path: &Path, format: Format, entries: Vec<(String, Entry)>) -> std::io::Result<()> {
+    if format == Format::Zip {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)?;
+        let mut zip = zip::ZipWriter::new_append(file)?;
+        write_zip(&mut zip, entries)?;
+        zip.finish()?;
+        return Ok(());
+    }
+
+    // tar archives and gzip members can just be concatenated
+    let file = std::fs::OpenOptions::new().append(true).open(path)?;
+    write_tar(&file, format, |builder| add_tar(builder, entries))
+}
+
+/// Write the archive without the removed entries and with the new ones into a temporary file,
+/// which replaces the archive afterwards
+fn rewrite(
+    path: &Path,
+    format: Format,
+    remove: impl Fn(&str) -> bool,
+    entries: Vec<(String, Entry)>,
+) -> std::io::Result<()> {
+    let temp = temp_path(path);
+    let res = rewrite_into(path, &temp, format, remove, entries);
+
+    match res {
+        Ok(_) => std::fs::rename(&temp, path),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+fn rewrite_into(
+    path: &Path,
+    temp: &Path,
+    format: Format,
+    remove: impl Fn(&str) -> bool,
+    entries: Vec<(String, Entry)>,
+) -> std::io::Result<()> {
+    // a new archive has no entries to keep
+    let names = match path.exists() {
+        true => names(path, format)?,
+        false => vec![],
+    };
+    let keep: BTreeSet<usize> = last_entries(&names)
+        .into_iter()
+        .filter(|(n, _)| !remove(n))
+        .map(|(_, i)| i)
+        .collect();
+
+    let file = std::fs::File::create(temp)?;
+
+    if format == Format::Zip {
+        let mut zip = zip::ZipWriter::new(file);
+        if !keep.is_empty() {
+            let mut archive = open_zip(path)?;
+            for i in keep {
+                zip.raw_copy_file(archive.by_index(i)?)?;
+            }
+        }
+        write_zip(&mut zip, entries)?;
+        return zip.finish()?.sync_all();
+    }
+
+    write_tar(&file, format, |builder| {
+        if !keep.is_empty() {
+            for (i, entry) in open_tar(path, format)?.entries()?.enumerate() {
+                let mut entry = entry?;
+                if keep.contains(&i) {
+                    let mut header = entry.header().clone();
+                    let name = entry.path()?.into_owned();
+                    builder.append_data(&mut header, name, &mut entry)?;
+                }
+            }
+        }
+        add_tar(builder, entries)
+    })
+}
+
+/// Write a tar archive into the file, which is gzip compressed depending on the format
+fn write_tar(
+    file: &std::fs::File,
+    format: Format,
+    build: impl FnOnce(&mut tar::Builder<&mut dyn Write>) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    match format {
+        Format::TarGz => {
+            let mut gz = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            build_tar(&mut gz, build)?;
+            gz.finish()?;
+        }
+        _ => build_tar(&mut &*file, build)?,
+    }
+    file.sync_all()
+}
+
+fn build_tar(
+    write: &mut dyn Write,
+    build: impl FnOnce(&mut tar::Builder<&mut dyn Write>) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let mut builder = tar::Builder::new(write);
+    build(&mut builder)?;
+    builder.finish()
+}
+
+/// Add the new entries to the tar archive
+fn add_tar(
+    builder: &mut tar::Builder<&mut dyn Write>,
+    entries: Vec<(String, Entry)>,
+) -> std::io::Result<()> {
+    let mtime = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    for (name, entry) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(mtime);
+
+        match entry {
+            Entry::Dir => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(0o755);
+                header.set_size(0);
+                builder.append_data(&mut header, name, std::io::empty())?;
+            }
+            Entry::File(spool) => {
+                let file = std::fs::File::open(spool)?;
+                header.set_mode(0o644);
+                header.set_size(file.metadata()?.len());
+                builder.append_data(&mut header, name, file)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Add the new entries to the zip archive
+fn write_zip<W: Write + std::io::Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    entries: Vec<(String, Entry)>,
+) -> std::io::Result<()> {
+    let options = zip::write::FileOptions::default();
+
+    for (name, entry) in entries {
+        match entry {
+            Entry::Dir => zip.add_directory(name, options)?,
+            Entry::File(spool) => {
+                zip.start_file(name, options)?;
+                std::io::copy(&mut std::fs::File::open(spool)?, zip)?;
+            }
+        }
+    }
+    Ok(())
+}
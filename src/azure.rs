@@ -0,0 +1,318 @@
+use crate::*;
+
+/// Size of the blocks an item gets uploaded in
+const BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Version of the Azure storage REST API which is used
+const API_VERSION: &str = "2019-12-12";
+
+/// Authentication method for the Azure blob storage
+#[derive(Clone)]
+pub enum AzureAuth {
+    /// The base64 encoded access key of the storage account
+    SharedKey(String),
+    /// A shared access signature query string, e.g. `sv=...&sig=...`
+    Sas(String),
+}
+
+#[derive(Clone)]
+pub struct Azure {
+    client: reqwest::Client,
+    account: String,
+    endpoint: String,
+    auth: AzureAuth,
+}
+
+impl Azure {
+    /// Create a new Azure blob storage connection to the given account
+    pub async fn new(account: &str, auth: AzureAuth) -> Result<Self> {
+        // validate the key early, to not fail on the first request
+        if let AzureAuth::SharedKey(key) = &auth {
+            base64::decode(key)?;
+        }
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            account: account.to_string(),
+            endpoint: format!("https://{}.blob.core.windows.net", account),
+            auth,
+        })
+    }
+
+    /// Use a custom endpoint instead of the default Azure one.
+    /// This is needed for the Azurite emulator, e.g. `http://127.0.0.1:10000/devstoreaccount1`
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Send a request against the given path, relative to the endpoint
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response> {
+        let mut url = reqwest::Url::parse(&self.endpoint)?;
+        url.path_segments_mut()
+            .map_err(|_| StowError::Unknown)?
+            .pop_if_empty()
+            .extend(path.split('/').filter(|s| !s.is_empty()));
+        url.query_pairs_mut().extend_pairs(query);
+        if let AzureAuth::Sas(sas) = &self.auth {
+            let sas = url::form_urlencoded::parse(sas.trim_start_matches('?').as_bytes());
+            url.query_pairs_mut().extend_pairs(sas);
+        }
+
+        let mut req = self.client.request(method, url).body(body).build()?;
+        let length = req.body().and_then(|b| b.as_bytes()).map_or(0, |b| b.len());
+
+        let headers = req.headers_mut();
+        headers.insert(reqwest::header::CONTENT_LENGTH, length.into());
+        headers.insert(
+            "x-ms-date",
+            httpdate::fmt_http_date(std::time::SystemTime::now()).parse()?,
+        );
+        headers.insert("x-ms-version", API_VERSION.parse()?);
+
+        if let AzureAuth::SharedKey(key) = &self.auth {
+            let signature = self.sign(&req, key)?;
+            req.headers_mut().insert(
+                reqwest::header::AUTHORIZATION,
+                format!("SharedKey {}:{}", self.account, signature).parse()?,
+            );
+        }
+
+        util::http_status(self.client.execute(req).await?)
+    }
+
+    /// Create the shared key signature for the request
+    /// See https://docs.microsoft.com/en-us/rest/api/storageservices/authorize-with-shared-key
+    fn sign(&self, req: &reqwest::Request, key: &str) -> Result<String> {
+        use hmac::{Mac, NewMac};
+
+        let header = |name: reqwest::header::HeaderName| {
+            req.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        // an empty body is signed with an empty content length
+        let mut length = header(reqwest::header::CONTENT_LENGTH);
+        if length == "0" {
+            length.clear();
+        }
+
+        let mut sign = [
+            req.method().as_str().to_string(),
+            header(reqwest::header::CONTENT_ENCODING),
+            header(reqwest::header::CONTENT_LANGUAGE),
+            length,
+            String::new(), // Content-MD5
+            header(reqwest::header::CONTENT_TYPE),
+            String::new(), // Date, x-ms-date is used instead
+            header(reqwest::header::IF_MODIFIED_SINCE),
+            header(reqwest::header::IF_MATCH),
+            header(reqwest::header::IF_NONE_MATCH),
+            header(reqwest::header::IF_UNMODIFIED_SINCE),
+            header(reqwest::header::RANGE),
+        ]
+        .join("\n");
+        sign.push('\n');
+
+        // canonicalized headers
+        let mut headers = req
+            .headers()
+            .iter()
+            .filter(|(k, _)| k.as_str().starts_with("x-ms-"))
+            .map(|(k, v)| format!("{}:{}\n", k, String::from_utf8_lossy(v.as_bytes()).trim()))
+            .collect::<Vec<_>>();
+        headers.sort();
+        headers.iter().for_each(|h| sign.push_str(h));
+
+        // canonicalized resource
+        sign.push('/');
+        sign.push_str(&self.account);
+        sign.push_str(req.url().path());
+
+        let mut query = std::collections::BTreeMap::<String, Vec<String>>::new();
+        for (k, v) in req.url().query_pairs() {
+            query
+                .entry(k.to_lowercase())
+                .or_default()
+                .push(v.to_string());
+        }
+        for (k, mut v) in query {
+            v.sort();
+            sign.push_str(&format!("\n{}:{}", k, v.join(",")));
+        }
+
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(&base64::decode(key)?)
+            .map_err(|_| StowError::Unknown)?;
+        mac.update(sign.as_bytes());
+        Ok(base64::encode(mac.finalize().into_bytes()))
+    }
+
+    /// Request a paged listing and collect all names of it
+    async fn list(&self, path: &str, query: &[(&str, &str)], parent: &str) -> Result<Vec<String>> {
+        let mut names = vec![];
+        let mut marker = String::new();
+
+        loop {
+            let mut q = query.to_vec();
+            if !marker.is_empty() {
+                q.push(("marker", &marker));
+            }
+
+            let res = self.send(reqwest::Method::GET, path, &q, vec![]).await?;
+            let body = res.text().await?;
+
+            names.append(&mut util::xml_values(&body, &[parent, "Name"])?);
+            marker = util::xml_values(&body, &["EnumerationResults", "NextMarker"])?
+                .pop()
+                .unwrap_or_default();
+
+            if marker.is_empty() {
+                return Ok(names);
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Azure {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.list("", &[("comp", "list")], "Container").await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        let res = self
+            .send(
+                reqwest::Method::PUT,
+                container,
+                &[("restype", "container")],
+                vec![],
+            )
+            .await;
+
+        match res {
+            // the container already exists - so no error
+            Err(StowError::HttpStatusError(409)) => Ok(()),
+            Err(e) => Err(e),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        let container = util::streamline(container);
+
+        self.send(
+            reqwest::Method::DELETE,
+            &container,
+            &[("restype", "container")],
+            vec![],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let container = util::streamline(container);
+
+        self.list(
+            &container,
+            &[("restype", "container"), ("comp", "list")],
+            "Blob",
+        )
+        .await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let item = util::streamline_item(item)?;
+        let path = format!("{}/{}", container, item);
+
+        // upload the item block by block, to never hold more than one block in memory
+        let mut blocks = vec![];
+        loop {
+            let mut block = Vec::with_capacity(BLOCK_SIZE);
+            (&mut reader)
+                .take(BLOCK_SIZE as u64)
+                .read_to_end(&mut block)
+                .await?;
+            if block.is_empty() {
+                break;
+            }
+
+            // all block ids of a blob need to have the same length
+            let id = base64::encode(format!("{:08}", blocks.len()));
+            self.send(
+                reqwest::Method::PUT,
+                &path,
+                &[("comp", "block"), ("blockid", &id)],
+                block,
+            )
+            .await?;
+            blocks.push(id);
+        }
+
+        // commit the uploaded blocks as the new content of the blob
+        let mut list = String::from(r#"<?xml version="1.0" encoding="utf-8"?><BlockList>"#);
+        for id in blocks {
+            list.push_str(&format!("<Latest>{}</Latest>", id));
+        }
+        list.push_str("</BlockList>");
+
+        self.send(
+            reqwest::Method::PUT,
+            &path,
+            &[("comp", "blocklist")],
+            list.into_bytes(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let res = self
+            .send(
+                reqwest::Method::GET,
+                &format!("{}/{}", container, item),
+                &[],
+                vec![],
+            )
+            .await?;
+
+        Ok(util::response_reader(res))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        self.send(
+            reqwest::Method::DELETE,
+            &format!("{}/{}", container, item),
+            &[],
+            vec![],
+        )
+        .await?;
+        Ok(())
+    }
+}
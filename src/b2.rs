@@ -0,0 +1,486 @@
+use crate::*;
+use std::sync::Arc;
+
+/// Default url of the B2 native API
+const API_URL: &str = "https://api.backblazeb2.com";
+
+/// Characters which are escaped in file names, `/` is kept as path separator
+const FILE_NAME: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'.')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Authorization of the account, which is received from `b2_authorize_account`
+struct Session {
+    account_id: String,
+    token: String,
+    api_url: String,
+    download_url: String,
+    recommended_part_size: usize,
+    minimum_part_size: usize,
+}
+
+/// Location on Backblaze B2, using the native API.
+/// Containers are buckets, items are files. Items bigger than the part size are uploaded as large files.
+#[derive(Clone)]
+pub struct B2 {
+    client: reqwest::Client,
+    api_url: String,
+    key_id: String,
+    application_key: String,
+    session: Arc<tokio::sync::RwLock<Session>>,
+    part_size: Option<usize>,
+}
+
+impl B2 {
+    pub async fn new(key_id: &str, application_key: &str) -> Result<Self> {
+        Self::new_with_url(API_URL, key_id, application_key).await
+    }
+
+    /// Use another url for the authorization, e.g. for a test server
+    pub async fn new_with_url(url: &str, key_id: &str, application_key: &str) -> Result<Self> {
+        let client = reqwest::Client::new();
+        let api_url = url.trim_end_matches('/').to_string();
+        let session = Self::authorize(&client, &api_url, key_id, application_key).await?;
+
+        Ok(Self {
+            client,
+            api_url,
+            key_id: key_id.to_string(),
+            application_key: application_key.to_string(),
+            session: Arc::new(tokio::sync::RwLock::new(session)),
+            part_size: None,
+        })
+    }
+
+    /// Set the size of the parts large files are uploaded in.
+    /// By default the recommended part size of the account is used.
+    pub fn with_part_size(mut self, part_size: usize) -> Self {
+        self.part_size = Some(part_size);
+        self
+    }
+
+    async fn authorize(
+        client: &reqwest::Client,
+        url: &str,
+        key_id: &str,
+        application_key: &str,
+    ) -> Result<Session> {
+        let res = client
+            .get(format!("{}/b2api/v2/b2_authorize_account", url))
+            .basic_auth(key_id, Some(application_key))
+            .send()
+            .await?;
+        let res: serde_json::Value = util::http_status(res)?.json().await?;
+
+        let field = |name: &str| {
+            res[name]
+                .as_str()
+                .map(|s| s.to_string())
+                .ok_or(StowError::B2ResponseError)
+        };
+        let size = |name: &str| {
+            res[name]
+                .as_u64()
+                .map(|s| s as usize)
+                .ok_or(StowError::B2ResponseError)
+        };
+
+        Ok(Session {
+            account_id: field("accountId")?,
+            token: field("authorizationToken")?,
+            api_url: field("apiUrl")?,
+            download_url: field("downloadUrl")?,
+            recommended_part_size: size("recommendedPartSize")?,
+            minimum_part_size: size("absoluteMinimumPartSize")?,
+        })
+    }
+
+    /// Send the request, which is created with the current session.
+    /// The request is repeated once with a new authorization, if the current one expired.
+    async fn send(
+        &self,
+        request: impl Fn(&Session) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        let mut retry = true;
+
+        loop {
+            let (res, token) = {
+                let session = self.session.read().await;
+                let req = request(&session).header(reqwest::header::AUTHORIZATION, &session.token);
+                (req.send().await?, session.token.clone())
+            };
+
+            if res.status() == reqwest::StatusCode::UNAUTHORIZED && retry {
+                retry = false;
+
+                // only authorize again, if no other task did it in the meantime
+                let mut session = self.session.write().await;
+                if session.token == token {
+                    *session = Self::authorize(
+                        &self.client,
+                        &self.api_url,
+                        &self.key_id,
+                        &self.application_key,
+                    )
+                    .await?;
+                }
+                continue;
+            }
+
+            return util::http_status(res);
+        }
+    }
+
+    /// Call the API operation with the given name
+    async fn api(&self, name: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        let res = self
+            .send(|s| {
+                self.client
+                    .post(format!("{}/b2api/v2/{}", s.api_url, name))
+                    .json(&body)
+            })
+            .await?;
+        Ok(res.json().await?)
+    }
+
+    async fn account_id(&self) -> String {
+        self.session.read().await.account_id.clone()
+    }
+
+    /// Get the id of the bucket with the given name
+    async fn bucket_id(&self, bucket: &str) -> Result<String> {
+        let res = self
+            .api(
+                "b2_list_buckets",
+                serde_json::json!({
+                    "accountId": self.account_id().await,
+                    "bucketName": bucket,
+                }),
+            )
+            .await?;
+
+        res["buckets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|b| b["bucketName"] == bucket)
+            .and_then(|b| b["bucketId"].as_str())
+            .map(|b| b.to_string())
+            .ok_or(StowError::HttpStatusError(404))
+    }
+
+    /// Get the name and id of all versions of the files, optionally only of the one with the given name
+    async fn versions(&self, bucket_id: &str, name: Option<&str>) -> Result<Vec<(String, String)>> {
+        let mut versions = vec![];
+        let mut body = serde_json::json!({ "bucketId": bucket_id, "maxFileCount": 1000 });
+        if let Some(name) = name {
+            body["startFileName"] = name.into();
+        }
+
+        loop {
+            let res = self.api("b2_list_file_versions", body.clone()).await?;
+
+            for file in res["files"].as_array().into_iter().flatten() {
+                let file_name = file["fileName"].as_str().unwrap_or_default();
+                let file_id = file["fileId"].as_str().unwrap_or_default();
+
+                // the listing starts at the name, but continues with the following files
+                if matches!(name, Some(n) if n != file_name) {
+                    return Ok(versions);
+                }
+                versions.push((file_name.to_string(), file_id.to_string()));
+            }
+
+            if res["nextFileName"].is_null() {
+                return Ok(versions);
+            }
+            body["startFileName"] = res["nextFileName"].clone();
+            body["startFileId"] = res["nextFileId"].clone();
+        }
+    }
+
+    async fn delete_version(&self, name: &str, id: &str) -> Result<()> {
+        self.api(
+            "b2_delete_file_version",
+            serde_json::json!({ "fileName": name, "fileId": id }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Upload the data to an upload url, which is received from the given API operation
+    async fn upload(
+        &self,
+        operation: &str,
+        body: serde_json::Value,
+        headers: &[(&str, String)],
+        data: Vec<u8>,
+    ) -> Result<serde_json::Value> {
+        use sha1::Digest;
+
+        let upload = self.api(operation, body).await?;
+        let url = upload["uploadUrl"]
+            .as_str()
+            .ok_or(StowError::B2ResponseError)?;
+        let token = upload["authorizationToken"]
+            .as_str()
+            .ok_or(StowError::B2ResponseError)?;
+
+        let mut req = self
+            .client
+            .post(url)
+            .header(reqwest::header::AUTHORIZATION, token)
+            .header(reqwest::header::CONTENT_LENGTH, data.len())
+            .header(
+                "X-Bz-Content-Sha1",
+                format!("{:x}", sha1::Sha1::digest(&data)),
+            );
+        for (name, value) in headers {
+            req = req.header(*name, value);
+        }
+
+        let res = req.body(data).send().await?;
+        Ok(util::http_status(res)?.json().await?)
+    }
+
+    /// Upload the item as large file, in parts of the given size
+    async fn upload_large(
+        &self,
+        bucket_id: &str,
+        item: &str,
+        mut part: Vec<u8>,
+        part_size: usize,
+        reader: &mut (impl tokio::io::AsyncRead + Unpin + Send),
+    ) -> Result<()> {
+        use sha1::Digest;
+        use tokio::io::AsyncReadExt;
+
+        let file = self
+            .api(
+                "b2_start_large_file",
+                serde_json::json!({
+                    "bucketId": bucket_id,
+                    "fileName": item,
+                    "contentType": "b2/x-auto",
+                }),
+            )
+            .await?;
+        let file_id = file["fileId"]
+            .as_str()
+            .ok_or(StowError::B2ResponseError)?
+            .to_string();
+
+        let upload = async {
+            let mut hashes = vec![];
+            while !part.is_empty() {
+                hashes.push(format!("{:x}", sha1::Sha1::digest(&part)));
+                self.upload(
+                    "b2_get_upload_part_url",
+                    serde_json::json!({ "fileId": file_id }),
+                    &[("X-Bz-Part-Number", hashes.len().to_string())],
+                    part,
+                )
+                .await?;
+
+                part = Vec::with_capacity(part_size);
+                reader.take(part_size as u64).read_to_end(&mut part).await?;
+            }
+
+            self.api(
+                "b2_finish_large_file",
+                serde_json::json!({ "fileId": file_id, "partSha1Array": hashes }),
+            )
+            .await
+        };
+
+        // don't leave unfinished large files behind
+        if let Err(e) = upload.await {
+            let _ = self
+                .api(
+                    "b2_cancel_large_file",
+                    serde_json::json!({ "fileId": file_id }),
+                )
+                .await;
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for B2 {
+    async fn containers(&self) -> Result<Vec<String>> {
+        let res = self
+            .api(
+                "b2_list_buckets",
+                serde_json::json!({ "accountId": self.account_id().await }),
+            )
+            .await?;
+
+        Ok(res["buckets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|b| b["bucketName"].as_str())
+            .map(|b| b.to_string())
+            .collect())
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        // only create bucket if not available
+        if self.bucket_id(container).await.is_ok() {
+            return Ok(());
+        }
+
+        self.api(
+            "b2_create_bucket",
+            serde_json::json!({
+                "accountId": self.account_id().await,
+                "bucketName": container,
+                "bucketType": "allPrivate",
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        let container = util::streamline(container);
+        let bucket_id = self.bucket_id(&container).await?;
+
+        // buckets need to be empty to be removed
+        for (name, id) in self.versions(&bucket_id, None).await? {
+            self.delete_version(&name, &id).await?;
+        }
+
+        self.api(
+            "b2_delete_bucket",
+            serde_json::json!({
+                "accountId": self.account_id().await,
+                "bucketId": bucket_id,
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let container = util::streamline(container);
+        let bucket_id = self.bucket_id(&container).await?;
+
+        let mut items = vec![];
+        let mut body = serde_json::json!({ "bucketId": bucket_id, "maxFileCount": 1000 });
+        loop {
+            let res = self.api("b2_list_file_names", body.clone()).await?;
+
+            items.extend(
+                res["files"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|f| f["fileName"].as_str())
+                    .map(|f| f.to_string()),
+            );
+
+            if res["nextFileName"].is_null() {
+                return Ok(items);
+            }
+            body["startFileName"] = res["nextFileName"].clone();
+        }
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let item = util::streamline_item(item)?;
+        let bucket_id = self.bucket_id(container).await?;
+
+        let part_size = {
+            let session = self.session.read().await;
+            self.part_size
+                .unwrap_or(session.recommended_part_size)
+                .max(session.minimum_part_size)
+        };
+
+        let mut part = Vec::with_capacity(part_size);
+        (&mut reader)
+            .take(part_size as u64)
+            .read_to_end(&mut part)
+            .await?;
+
+        // items bigger than one part are uploaded as large file
+        let mut next = vec![];
+        (&mut reader).take(1).read_to_end(&mut next).await?;
+        if !next.is_empty() {
+            return self
+                .upload_large(
+                    &bucket_id,
+                    &item,
+                    part,
+                    part_size,
+                    &mut (&next[..]).chain(reader),
+                )
+                .await;
+        }
+
+        self.upload(
+            "b2_get_upload_url",
+            serde_json::json!({ "bucketId": bucket_id }),
+            &[
+                (
+                    "X-Bz-File-Name",
+                    percent_encoding::utf8_percent_encode(&item, FILE_NAME).to_string(),
+                ),
+                (reqwest::header::CONTENT_TYPE.as_str(), "b2/x-auto".into()),
+            ],
+            part,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let res = self
+            .send(|s| {
+                self.client.get(format!(
+                    "{}/file/{}/{}",
+                    s.download_url,
+                    container,
+                    percent_encoding::utf8_percent_encode(&item, FILE_NAME)
+                ))
+            })
+            .await?;
+        Ok(util::response_reader(res))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+        let bucket_id = self.bucket_id(&container).await?;
+
+        // remove all versions, to not keep the item as hidden file
+        let versions = self.versions(&bucket_id, Some(&item)).await?;
+        if versions.is_empty() {
+            return Err(StowError::HttpStatusError(404));
+        }
+        for (name, id) in versions {
+            self.delete_version(&name, &id).await?;
+        }
+        Ok(())
+    }
+}
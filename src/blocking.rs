@@ -0,0 +1,158 @@
+//! Synchronous facade over [`crate::Location`], for code which doesn't run in an async runtime.
+//!
+//! The location owns a Tokio runtime and blocks on it for every operation,
+//! so it must not be used from within an async context.
+
+use crate::{Result, StowError};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Size of the buffer between an item writer and the upload
+const WRITER_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A storage location with synchronous methods
+#[derive(Clone)]
+pub struct Location {
+    inner: crate::Location,
+    runtime: Arc<tokio::runtime::Runtime>,
+}
+
+impl Location {
+    /// Create the location with one of the async constructors, e.g.
+    /// `blocking::Location::new(stow::Location::new_local("./data"))`
+    pub fn new(
+        location: impl std::future::Future<Output = Result<crate::Location>>,
+    ) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        Ok(Self {
+            inner: runtime.block_on(location)?,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    pub fn containers(&self) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.containers())
+    }
+
+    pub fn create_container(&self, container: &str) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.create_container(container))
+    }
+
+    pub fn remove_container(&self, container: &str) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.remove_container(container))
+    }
+
+    pub fn items(&self, container: &str) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.items(container))
+    }
+
+    /// Create the item with all data of the reader
+    pub fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl std::io::Read,
+    ) -> Result<()> {
+        let mut writer = self.item_writer(container, item)?;
+        std::io::copy(&mut reader, &mut writer)?;
+        writer.finish()
+    }
+
+    /// Create the item with the data, which is written to the returned writer.
+    /// The item is complete, when `finish` is called on it.
+    pub fn item_writer(&self, container: &str, item: &str) -> Result<ItemWriter> {
+        let (writer, reader) = tokio::io::duplex(WRITER_BUFFER_SIZE);
+
+        let inner = self.inner.clone();
+        let (container, item) = (container.to_string(), item.to_string());
+        let upload = self
+            .runtime
+            .spawn(async move { inner.create_item(&container, &item, reader).await });
+
+        Ok(ItemWriter {
+            writer: Some(writer),
+            upload,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    pub fn read_item(&self, container: &str, item: &str) -> Result<ItemReader> {
+        Ok(ItemReader {
+            reader: self
+                .runtime
+                .block_on(self.inner.read_item(container, item))?,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    pub fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.remove_item(container, item))
+    }
+}
+
+/// Synchronous reader of an item
+pub struct ItemReader {
+    reader: Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>,
+    runtime: Arc<tokio::runtime::Runtime>,
+}
+
+impl std::io::Read for ItemReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let reader = &mut self.reader;
+        self.runtime.block_on(reader.read(buf))
+    }
+}
+
+/// Synchronous writer of a new item.
+/// Dropping it without calling `finish` cancels the upload.
+pub struct ItemWriter {
+    writer: Option<tokio::io::DuplexStream>,
+    upload: tokio::task::JoinHandle<Result<()>>,
+    runtime: Arc<tokio::runtime::Runtime>,
+}
+
+impl ItemWriter {
+    /// Complete the item and wait until it is stored
+    pub fn finish(mut self) -> Result<()> {
+        let mut writer = self.writer.take().ok_or(StowError::Unknown)?;
+        let upload = &mut self.upload;
+
+        self.runtime.block_on(async move {
+            // the upload can fail before all data is written
+            if let Err(e) = writer.shutdown().await {
+                return upload.await?.and(Err(e.into()));
+            }
+            drop(writer);
+            upload.await?
+        })
+    }
+}
+
+impl std::io::Write for ItemWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        self.runtime.block_on(writer.write(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for ItemWriter {
+    fn drop(&mut self) {
+        // cancel the upload before the end of the data is signaled to it
+        if self.writer.is_some() {
+            self.upload.abort();
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use crate::*;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Container of the cache location, which holds the cached items
+pub const CACHE_CONTAINER: &str = "stow-cache";
+
+/// Default limit of the total size of the cached items
+const MAX_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Cached copy of an item
+#[derive(Debug, Clone)]
+struct Entry {
+    /// Name of the copy in the cache container
+    name: String,
+    etag: Option<String>,
+    size: u64,
+    fetched: Instant,
+    /// Value of the use counter, when the entry was used last
+    used: u64,
+}
+
+/// Entries by container and item, with their total size
+#[derive(Debug, Default)]
+struct Index {
+    entries: HashMap<(String, String), Entry>,
+    size: u64,
+    uses: u64,
+    /// Number of invalidations, fetches which overlap one don't store their copy
+    writes: u64,
+    /// Number of created copies, to give every copy its own name
+    copies: u64,
+}
+
+/// Location wrapper, which keeps copies of read items in a cache location,
+/// e.g. a local folder or memory, to read them from there next time.
+///
+/// Copies are stored by container, item and, when the wrapped location provides it, the ETag.
+/// When the total size of the copies exceeds the limit, the least recently used ones are removed.
+/// After the TTL, a copy is only used if its ETag is unchanged, or it is fetched again.
+/// Without a TTL, copies are used until they are evicted or invalidated.
+///
+/// Creating or removing items through the wrapper invalidates their copies,
+/// changes made directly on the wrapped location are only noticed after the TTL.
+/// The index of the copies is kept in memory, so the cache container is cleared on creation.
+#[derive(Clone)]
+pub struct Cache {
+    pub(crate) inner: Arc<Location>,
+    cache: Arc<Location>,
+    index: Arc<Mutex<Index>>,
+    max_size: u64,
+    ttl: Option<Duration>,
+}
+
+impl Cache {
+    /// Wrap the location and clear the cache container in the cache location
+    pub async fn new(inner: Location, cache: Location) -> Result<Self> {
+        if cache
+            .containers()
+            .await?
+            .iter()
+            .any(|c| c == CACHE_CONTAINER)
+        {
+            cache.remove_container(CACHE_CONTAINER).await?;
+        }
+        cache.create_container(CACHE_CONTAINER).await?;
+
+        Ok(Self {
+            inner: Arc::new(inner),
+            cache: Arc::new(cache),
+            index: Arc::new(Mutex::new(Index::default())),
+            max_size: MAX_SIZE,
+            ttl: None,
+        })
+    }
+
+    /// Set the limit of the total size of the cached items
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Set how long cached items are used, before they are validated
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Get the ETag of the item, if the wrapped location provides it
+    #[cfg_attr(not(any(feature = "gcs", feature = "http")), allow(unused_variables))]
+    async fn etag(&self, container: &str, item: &str) -> Result<Option<String>> {
+        match &*self.inner {
+            #[cfg(feature = "gcs")]
+            Location::Gcs(l) => l.etag(container, item).await,
+            #[cfg(feature = "http")]
+            Location::Http(l) => Ok(l.stat_item(container, item).await?.etag),
+            _ => Ok(None),
+        }
+    }
+
+    /// Get the entry of the item and mark it as used
+    fn lookup(&self, container: &str, item: &str) -> Result<Option<Entry>> {
+        let mut index = self.index.lock().map_err(|_| StowError::Unknown)?;
+        index.uses += 1;
+        let uses = index.uses;
+
+        Ok(index.entries.get_mut(&key(container, item)?).map(|entry| {
+            entry.used = uses;
+            entry.clone()
+        }))
+    }
+
+    /// Check if the entry can be used without validation
+    fn fresh(&self, entry: &Entry) -> bool {
+        match self.ttl {
+            Some(ttl) => entry.fetched.elapsed() < ttl,
+            None => true,
+        }
+    }
+
+    /// Mark the entry as validated now
+    fn refresh(&self, container: &str, item: &str) -> Result<()> {
+        let mut index = self.index.lock().map_err(|_| StowError::Unknown)?;
+        if let Some(entry) = index.entries.get_mut(&key(container, item)?) {
+            entry.fetched = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Store the entry and return the copies which need to be removed,
+    /// the replaced one and the least recently used ones above the size limit.
+    /// Nothing is stored, if items were invalidated since the given number of writes,
+    /// because the copy could hold their old data
+    fn insert(
+        &self,
+        container: &str,
+        item: &str,
+        entry: Entry,
+        writes: u64,
+    ) -> Result<Option<Vec<String>>> {
+        let mut index = self.index.lock().map_err(|_| StowError::Unknown)?;
+        if index.writes != writes {
+            return Ok(None);
+        }
+        index.uses += 1;
+        let used = index.uses;
+
+        let mut removed = vec![];
+        let name = entry.name.clone();
+        index.size += entry.size;
+        if let Some(old) = index
+            .entries
+            .insert(key(container, item)?, Entry { used, ..entry })
+        {
+            index.size -= old.size;
+            if old.name != name {
+                removed.push(old.name);
+            }
+        }
+
+        while index.size > self.max_size {
+            let oldest = index
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.used)
+                .map(|(k, _)| k.clone());
+            let entry = match oldest.and_then(|k| index.entries.remove(&k)) {
+                Some(entry) => entry,
+                None => break,
+            };
+            index.size -= entry.size;
+            removed.push(entry.name);
+        }
+        Ok(Some(removed))
+    }
+
+    /// Remove the entries of the container, or only of the item, and their copies.
+    /// It is called before and after changes, so reads in the meantime don't keep the old data
+    async fn invalidate(&self, container: &str, item: Option<&str>) -> Result<()> {
+        let item = item.map(util::streamline_item).transpose()?;
+        let removed = {
+            let mut index = self.index.lock().map_err(|_| StowError::Unknown)?;
+            index.writes += 1;
+            let container = util::streamline(container);
+            let keys = index
+                .entries
+                .keys()
+                .filter(|(c, i)| *c == container && item.iter().all(|item| i == item))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let mut removed = vec![];
+            for key in keys {
+                if let Some(entry) = index.entries.remove(&key) {
+                    index.size -= entry.size;
+                    removed.push(entry.name);
+                }
+            }
+            removed
+        };
+
+        self.remove_copies(removed).await;
+        Ok(())
+    }
+
+    /// Remove copies from the cache location, a failure only wastes space
+    async fn remove_copies(&self, names: Vec<String>) {
+        for name in names {
+            let _ = self.cache.remove_item(CACHE_CONTAINER, &name).await;
+        }
+    }
+
+    /// Read the item from the wrapped location and store a copy of it
+    async fn fetch(
+        &self,
+        container: &str,
+        item: &str,
+        etag: Option<String>,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        use sha2::Digest;
+
+        let (writes, copy) = {
+            let mut index = self.index.lock().map_err(|_| StowError::Unknown)?;
+            index.copies += 1;
+            (index.writes, index.copies)
+        };
+
+        let (c, i) = key(container, item)?;
+        let id = format!("{}\n{}\n{}", c, i, etag.as_deref().unwrap_or_default());
+        let name = sha2::Sha256::digest(id.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        // every copy gets its own name, to not overwrite a copy which is still read,
+        // and items need a file ending
+        let name = format!("{}-{}.cache", name, copy);
+
+        let (reader, size) =
+            util::CountingReader::new(self.inner.read_item(container, item).await?);
+        self.cache
+            .create_item(CACHE_CONTAINER, &name, reader)
+            .await?;
+
+        // open the copy before evicting, it could be evicted right away when it is too big
+        let reader = self.cache.read_item(CACHE_CONTAINER, &name).await?;
+
+        let copy_name = name.clone();
+        let entry = Entry {
+            name,
+            etag,
+            size: size.load(Ordering::SeqCst),
+            fetched: Instant::now(),
+            used: 0,
+        };
+        match self.insert(container, item, entry, writes)? {
+            Some(removed) => {
+                self.remove_copies(removed).await;
+                Ok(reader)
+            }
+            // the item changed while it was copied, so it is read again without keeping it
+            None => {
+                drop(reader);
+                self.remove_copies(vec![copy_name]).await;
+                self.inner.read_item(container, item).await
+            }
+        }
+    }
+}
+
+/// Key of the entry of the item, the names are normalized like on creation,
+/// so differently written names of the same item share the entry
+fn key(container: &str, item: &str) -> Result<(String, String)> {
+    Ok((util::streamline(container), util::streamline_item(item)?))
+}
+
+#[async_trait::async_trait]
+impl Adapter for Cache {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.inner.containers().await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        self.inner.create_container(container).await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        self.invalidate(container, None).await?;
+        let res = self.inner.remove_container(container).await;
+        self.invalidate(container, None).await?;
+        res
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        self.inner.items(container).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        reader: impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
+    ) -> Result<()> {
+        self.invalidate(container, Some(item)).await?;
+        let res = self.inner.create_item(container, item, reader).await;
+        self.invalidate(container, Some(item)).await?;
+        res
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let entry = match self.lookup(container, item)? {
+            Some(entry) => entry,
+            None => {
+                let etag = self.etag(container, item).await?;
+                return self.fetch(container, item, etag).await;
+            }
+        };
+
+        // validate the copy, when it is too old
+        let mut etag = entry.etag.clone();
+        if !self.fresh(&entry) {
+            etag = self.etag(container, item).await?;
+            if etag.is_none() || etag != entry.etag {
+                return self.fetch(container, item, etag).await;
+            }
+            self.refresh(container, item)?;
+        }
+
+        // the copy could be removed in the meantime
+        match self.cache.read_item(CACHE_CONTAINER, &entry.name).await {
+            Ok(reader) => Ok(reader),
+            Err(_) => self.fetch(container, item, etag).await,
+        }
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        self.invalidate(container, Some(item)).await?;
+        let res = self.inner.remove_item(container, item).await;
+        self.invalidate(container, Some(item)).await?;
+        res
+    }
+}
@@ -0,0 +1,137 @@
+use crate::*;
+use async_compression::tokio::bufread::{GzipDecoder, GzipEncoder, ZstdDecoder, ZstdEncoder};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+/// Start of every compressed item, followed by the codec
+const MAGIC: &[u8; 8] = b"STOWCMP1";
+
+/// Compression format of items
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    fn id(self) -> u8 {
+        match self {
+            Codec::Gzip => 1,
+            Codec::Zstd => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Codec::Gzip),
+            2 => Some(Codec::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Location wrapper, which compresses items before they are stored in the wrapped location.
+///
+/// The codec is recorded in a header at the start of every item, so items can be read
+/// regardless of the codec they were created with. Items without the header,
+/// e.g. created before the wrapper was used, are read as they are.
+#[derive(Clone)]
+pub struct Compressed {
+    pub(crate) inner: Arc<Location>,
+    codec: Codec,
+}
+
+impl Compressed {
+    /// Wrap the location, to compress new items with the codec
+    pub fn new(inner: Location, codec: Codec) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            codec,
+        }
+    }
+
+    /// Get the codec the item is compressed with, or none if it is stored uncompressed
+    pub async fn codec(&self, container: &str, item: &str) -> Result<Option<Codec>> {
+        let mut reader = self.inner.read_item(container, item).await?;
+        Ok(read_header(&mut reader).await?.0)
+    }
+}
+
+/// Read the header at the start of an item and return its codec,
+/// for items without the header the already read data is returned as well
+async fn read_header(reader: &mut (impl AsyncRead + Unpin)) -> Result<(Option<Codec>, Vec<u8>)> {
+    let mut header = Vec::with_capacity(MAGIC.len() + 1);
+    reader
+        .take(MAGIC.len() as u64 + 1)
+        .read_to_end(&mut header)
+        .await?;
+
+    match header.split_last() {
+        Some((id, magic)) if magic == MAGIC => match Codec::from_id(*id) {
+            Some(codec) => Ok((Some(codec), vec![])),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "unknown compression codec",
+            )
+            .into()),
+        },
+        _ => Ok((None, header)),
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Compressed {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.inner.containers().await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        self.inner.create_container(container).await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        self.inner.remove_container(container).await
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        self.inner.items(container).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        reader: impl AsyncRead + Unpin + Send + Sync + 'static,
+    ) -> Result<()> {
+        let mut header = MAGIC.to_vec();
+        header.push(self.codec.id());
+        let header = std::io::Cursor::new(header);
+
+        // boxed, so wrapped locations don't nest the reader types endlessly
+        let reader = BufReader::new(reader);
+        let reader: Box<dyn AsyncRead + Unpin + Send + Sync> = match self.codec {
+            Codec::Gzip => Box::new(header.chain(GzipEncoder::new(reader))),
+            Codec::Zstd => Box::new(header.chain(ZstdEncoder::new(reader))),
+        };
+
+        self.inner.create_item(container, item, reader).await
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send + Sync>> {
+        let mut reader = self.inner.read_item(container, item).await?;
+
+        Ok(match read_header(&mut reader).await? {
+            (Some(Codec::Gzip), _) => Box::new(GzipDecoder::new(BufReader::new(reader))),
+            (Some(Codec::Zstd), _) => Box::new(ZstdDecoder::new(BufReader::new(reader))),
+            (None, data) => Box::new(std::io::Cursor::new(data).chain(reader)),
+        })
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        self.inner.remove_item(container, item).await
+    }
+}
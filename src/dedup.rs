@@ -0,0 +1,364 @@
+use crate::*;
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
+
+/// Container of the underlying location, which holds the chunks by their hash
+pub const DEDUP_CONTAINER: &str = "dedup-chunks";
+
+/// Name of the item in the chunks container, which holds the chunk with the given hash
+fn chunk_item(hash: &str) -> String {
+    // items need a file ending
+    format!("{}.chunk", hash)
+}
+
+/// Hash of the chunk data, which is its name as well
+fn chunk_hash(data: &[u8]) -> String {
+    use sha2::Digest;
+
+    sha2::Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Refuse the container of the chunks, which is only used internally
+fn check_container(container: &str) -> Result<()> {
+    if util::streamline(container) == DEDUP_CONTAINER {
+        return Err(StowError::InvalidNameError(container.to_string()));
+    }
+    Ok(())
+}
+
+/// Read the chunk with the given hash and check that its data still has this hash
+async fn read_chunk(inner: &Location, hash: &str) -> std::io::Result<bytes::Bytes> {
+    use tokio::io::AsyncReadExt;
+
+    let mut data = vec![];
+    inner
+        .read_item(DEDUP_CONTAINER, &chunk_item(hash))
+        .await
+        .map_err(util::io_error)?
+        .read_to_end(&mut data)
+        .await?;
+
+    if chunk_hash(&data) != hash {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("chunk {} is corrupted", hash),
+        ));
+    }
+    Ok(data.into())
+}
+
+/// How items are split into chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunking {
+    /// Chunks of the same size, only identical items and prefixes are deduplicated
+    Fixed(usize),
+    /// Chunk boundaries depend on the content, so shifted data is deduplicated as well
+    ContentDefined { min: usize, avg: usize, max: usize },
+}
+
+impl Default for Chunking {
+    fn default() -> Self {
+        Chunking::ContentDefined {
+            min: 256 * 1024,
+            avg: 1024 * 1024,
+            max: 4 * 1024 * 1024,
+        }
+    }
+}
+
+impl Chunking {
+    /// The biggest possible chunk
+    fn max(&self) -> usize {
+        match *self {
+            Chunking::Fixed(size) => size.max(1),
+            Chunking::ContentDefined { min, max, .. } => max.max(min).max(1),
+        }
+    }
+
+    /// Get the length of the next chunk at the start of the data
+    fn cut(&self, data: &[u8], gear: &[u64; 256]) -> usize {
+        let (min, avg) = match *self {
+            Chunking::Fixed(_) => return data.len().min(self.max()),
+            Chunking::ContentDefined { min, avg, .. } => (min, avg),
+        };
+
+        // a boundary is found, when the high bits of the gear hash are zero,
+        // which happens about every avg bytes
+        let bits = avg.max(2).next_power_of_two().trailing_zeros();
+        let mask = !(u64::MAX >> bits);
+
+        let mut hash = 0u64;
+        for (i, b) in data.iter().take(self.max()).enumerate() {
+            hash = (hash << 1).wrapping_add(gear[*b as usize]);
+            if i + 1 >= min && hash & mask == 0 {
+                return i + 1;
+            }
+        }
+        data.len().min(self.max())
+    }
+}
+
+/// Random but fixed values for every byte, used by the rolling hash
+fn gear() -> [u64; 256] {
+    // splitmix64 with a constant seed, the chunks need to be the same on every run
+    let mut state = 0x5354_4f57_4445_4455u64;
+    let mut gear = [0; 256];
+    for value in gear.iter_mut() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        *value = z ^ (z >> 31);
+    }
+    gear
+}
+
+/// List of the chunks of an item, stored instead of its data
+#[derive(Debug, Default)]
+struct Manifest {
+    size: u64,
+    chunks: Vec<String>,
+}
+
+impl Manifest {
+    fn to_json(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&serde_json::json!({
+            "size": self.size,
+            "chunks": self.chunks,
+        }))?)
+    }
+
+    fn from_json(data: &[u8]) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_slice(data)?;
+
+        Ok(Self {
+            size: value["size"].as_u64().unwrap_or_default(),
+            chunks: value["chunks"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|c| c.as_str())
+                .map(|c| c.to_string())
+                .collect(),
+        })
+    }
+}
+
+/// Location wrapper, which splits items into chunks and stores every chunk only once by its hash.
+/// The items of the underlying location are manifests, which list the chunks of the item.
+/// Chunks are checked against their hash, when they are read.
+///
+/// Removing items or containers keeps their chunks, because other items could still use them.
+/// Unused chunks are removed by `collect_garbage`.
+///
+/// The stored chunks are listed on the first write and remembered afterwards,
+/// so chunks removed by others than this wrapper and its clones are not noticed.
+#[derive(Clone)]
+pub struct Dedup {
+    pub(crate) inner: Arc<Location>,
+    chunking: Chunking,
+    chunks: Arc<std::sync::Mutex<Option<HashSet<String>>>>,
+}
+
+impl Dedup {
+    /// Wrap the location and create the container for the chunks in it
+    pub async fn new(inner: Location) -> Result<Self> {
+        inner.create_container(DEDUP_CONTAINER).await?;
+
+        Ok(Self {
+            inner: Arc::new(inner),
+            chunking: Chunking::default(),
+            chunks: Default::default(),
+        })
+    }
+
+    /// Set how new items are split into chunks
+    pub fn with_chunking(mut self, chunking: Chunking) -> Self {
+        self.chunking = chunking;
+        self
+    }
+
+    /// Read the manifest of the item
+    async fn manifest(&self, container: &str, item: &str) -> Result<Manifest> {
+        use tokio::io::AsyncReadExt;
+
+        let mut data = vec![];
+        self.inner
+            .read_item(container, item)
+            .await?
+            .read_to_end(&mut data)
+            .await?;
+        Manifest::from_json(&data)
+    }
+
+    /// Names of the stored chunks, if they were listed already
+    fn chunks(&self) -> Result<std::sync::MutexGuard<'_, Option<HashSet<String>>>> {
+        self.chunks.lock().map_err(|_| StowError::Unknown)
+    }
+
+    /// Check if the chunk is stored, the chunks are only listed the first time
+    async fn is_stored(&self, item: &str) -> Result<bool> {
+        if let Some(chunks) = self.chunks()?.as_ref() {
+            return Ok(chunks.contains(item));
+        }
+
+        let listed: HashSet<String> = self
+            .inner
+            .items(DEDUP_CONTAINER)
+            .await?
+            .into_iter()
+            .collect();
+        let stored = listed.contains(item);
+        self.chunks()?.get_or_insert(listed);
+        Ok(stored)
+    }
+
+    /// Store the chunk, if it is not stored yet, and return its hash
+    async fn store_chunk(&self, chunk: Vec<u8>) -> Result<String> {
+        let hash = chunk_hash(&chunk);
+
+        let item = chunk_item(&hash);
+        if !self.is_stored(&item).await? {
+            self.inner
+                .create_item(DEDUP_CONTAINER, &item, std::io::Cursor::new(chunk))
+                .await?;
+            if let Some(chunks) = self.chunks()?.as_mut() {
+                chunks.insert(item);
+            }
+        }
+        Ok(hash)
+    }
+
+    /// Remove all chunks, which are not used by any item, and return their number.
+    /// Items which get created in the meantime can lose their chunks, so no writes should happen during it.
+    pub async fn collect_garbage(&self) -> Result<usize> {
+        let mut used: BTreeSet<String> = BTreeSet::new();
+        for container in self.containers().await? {
+            for item in self.inner.items(&container).await? {
+                used.extend(self.manifest(&container, &item).await?.chunks);
+            }
+        }
+
+        let mut removed = 0;
+        for item in self.inner.items(DEDUP_CONTAINER).await? {
+            if !used.contains(item.trim_end_matches(".chunk")) {
+                self.inner.remove_item(DEDUP_CONTAINER, &item).await?;
+                if let Some(chunks) = self.chunks()?.as_mut() {
+                    chunks.remove(&item);
+                }
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Dedup {
+    async fn containers(&self) -> Result<Vec<String>> {
+        Ok(self
+            .inner
+            .containers()
+            .await?
+            .into_iter()
+            .filter(|c| c != DEDUP_CONTAINER)
+            .collect())
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        check_container(container)?;
+        self.inner.create_container(container).await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        check_container(container)?;
+        self.inner.remove_container(container).await
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        check_container(container)?;
+        self.inner.items(container).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        check_container(container)?;
+
+        let gear = gear();
+        let max = self.chunking.max();
+
+        let mut manifest = Manifest::default();
+        let mut pending = Vec::with_capacity(max);
+        let mut eof = false;
+
+        loop {
+            // fill the window, so the boundary of the next chunk can be found in it
+            if !eof && pending.len() < max {
+                let wanted = max - pending.len();
+                let read = (&mut reader)
+                    .take(wanted as u64)
+                    .read_to_end(&mut pending)
+                    .await?;
+                eof = read < wanted;
+                continue;
+            }
+            if pending.is_empty() {
+                break;
+            }
+
+            let len = self.chunking.cut(&pending, &gear);
+            let rest = pending.split_off(len);
+            let chunk = std::mem::replace(&mut pending, rest);
+
+            manifest.size += chunk.len() as u64;
+            manifest.chunks.push(self.store_chunk(chunk).await?);
+        }
+
+        self.inner
+            .create_item(container, item, std::io::Cursor::new(manifest.to_json()?))
+            .await
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        use futures::SinkExt;
+
+        check_container(container)?;
+        let manifest = self.manifest(container, item).await?;
+
+        // the chunks are read one after another by a task, which forwards their data,
+        // once it is verified against the hash
+        let inner = self.inner.clone();
+        let (mut send, recv) = futures::channel::mpsc::channel(4);
+
+        tokio::spawn(async move {
+            for hash in manifest.chunks {
+                // stop on errors or when the reader got dropped
+                let data = read_chunk(&inner, &hash).await;
+                let failed = data.is_err();
+                if send.send(data).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        Ok(Box::new(tokio_util::io::StreamReader::new(recv)))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        check_container(container)?;
+        self.inner.remove_item(container, item).await
+    }
+}
@@ -0,0 +1,326 @@
+use crate::*;
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{OsRng, Payload};
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Start of every encrypted item
+const MAGIC: &[u8; 8] = b"STOWENC1";
+
+/// Size of the plaintext chunks, which are encrypted one after another
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Size of the authentication tag of every chunk
+const TAG_SIZE: usize = 16;
+
+/// Size of the random part of the nonces, the rest is the chunk counter and the last chunk flag
+const NONCE_PREFIX_SIZE: usize = 19;
+
+/// Keys to encrypt and decrypt items by their id.
+/// New items are encrypted with the current key, the others are only used to read older items.
+#[derive(Clone)]
+pub struct Keyring {
+    current: String,
+    keys: HashMap<String, [u8; 32]>,
+}
+
+impl Keyring {
+    /// Create the keyring with the key, which encrypts new items
+    pub fn new(id: &str, key: [u8; 32]) -> Self {
+        let mut keys = HashMap::new();
+        keys.insert(id.to_string(), key);
+
+        Self {
+            current: id.to_string(),
+            keys,
+        }
+    }
+
+    /// Add a key, which is only used to read the items encrypted with it
+    pub fn with_key(mut self, id: &str, key: [u8; 32]) -> Self {
+        self.keys.entry(id.to_string()).or_insert(key);
+        self
+    }
+
+    /// Id of the key, which encrypts new items
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    fn cipher(&self, id: &str) -> Result<XChaCha20Poly1305> {
+        let key = self
+            .keys
+            .get(id)
+            .ok_or_else(|| StowError::UnknownKeyError(id.to_string()))?;
+        Ok(XChaCha20Poly1305::new(GenericArray::from_slice(key)))
+    }
+}
+
+/// Location wrapper, which encrypts items before they are stored in the wrapped location.
+///
+/// The data is split into chunks, which are encrypted and authenticated with XChaCha20-Poly1305
+/// in the STREAM construction, so reordered, modified or truncated items fail to read.
+/// Every item starts with a header, which holds the id of its key and the random nonce prefix.
+///
+/// To rotate keys, create the wrapper with a keyring of the new key and the old ones,
+/// then call `rotate_item` for the items which are still encrypted with an old key.
+#[derive(Clone)]
+pub struct Encrypted {
+    pub(crate) inner: Arc<Location>,
+    keys: Arc<Keyring>,
+}
+
+impl Encrypted {
+    /// Wrap the location, to encrypt its items with the keys
+    pub fn new(inner: Location, keys: Keyring) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            keys: Arc::new(keys),
+        }
+    }
+
+    /// Get the id of the key the item is encrypted with
+    pub async fn key_id(&self, container: &str, item: &str) -> Result<String> {
+        let mut reader = self.inner.read_item(container, item).await?;
+        Ok(read_header(&mut reader).await?.0)
+    }
+
+    /// Encrypt the item again with the current key, if it is encrypted with another one.
+    /// The item is buffered in memory, to not overwrite it while it is read.
+    pub async fn rotate_item(&self, container: &str, item: &str) -> Result<bool> {
+        use tokio::io::AsyncReadExt;
+
+        if self.key_id(container, item).await? == self.keys.current() {
+            return Ok(false);
+        }
+
+        let mut data = vec![];
+        self.read_item(container, item)
+            .await?
+            .read_to_end(&mut data)
+            .await?;
+        self.create_item(container, item, std::io::Cursor::new(data))
+            .await?;
+        Ok(true)
+    }
+}
+
+/// Create the header of an item, it is authenticated with every chunk
+fn header(key_id: &str, nonce_prefix: &[u8]) -> Result<Vec<u8>> {
+    if key_id.len() > u8::MAX as usize {
+        return Err(StowError::UnknownKeyError(key_id.to_string()));
+    }
+
+    let mut header = MAGIC.to_vec();
+    header.push(key_id.len() as u8);
+    header.extend_from_slice(key_id.as_bytes());
+    header.extend_from_slice(nonce_prefix);
+    Ok(header)
+}
+
+/// Read the header at the start of an item and return the key id, the nonce prefix and the header itself
+async fn read_header(
+    reader: &mut (impl tokio::io::AsyncRead + Unpin),
+) -> Result<(String, Vec<u8>, Vec<u8>)> {
+    use tokio::io::AsyncReadExt;
+
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic).await?;
+    if &magic != MAGIC {
+        return Err(invalid_data("the item is not encrypted").into());
+    }
+
+    let mut id = vec![0; reader.read_u8().await? as usize];
+    reader.read_exact(&mut id).await?;
+    let id = String::from_utf8(id).map_err(|_| invalid_data("invalid key id"))?;
+
+    let mut nonce_prefix = vec![0; NONCE_PREFIX_SIZE];
+    reader.read_exact(&mut nonce_prefix).await?;
+
+    let header = header(&id, &nonce_prefix)?;
+    Ok((id, nonce_prefix, header))
+}
+
+fn invalid_data(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+/// Read the next chunk, it is only shorter than the chunk size at the end of the reader
+async fn read_chunk(reader: &mut (impl tokio::io::AsyncRead + Unpin)) -> std::io::Result<Vec<u8>> {
+    use tokio::io::AsyncReadExt;
+
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    reader
+        .take(CHUNK_SIZE as u64)
+        .read_to_end(&mut chunk)
+        .await?;
+    Ok(chunk)
+}
+
+#[async_trait::async_trait]
+impl Adapter for Encrypted {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.inner.containers().await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        self.inner.create_container(container).await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        self.inner.remove_container(container).await
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        self.inner.items(container).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
+    ) -> Result<()> {
+        use futures::SinkExt;
+
+        let mut nonce_prefix = [0; NONCE_PREFIX_SIZE];
+        OsRng.fill_bytes(&mut nonce_prefix);
+
+        let header = header(self.keys.current(), &nonce_prefix)?;
+        let mut encryptor = EncryptorBE32::from_aead(
+            self.keys.cipher(self.keys.current())?,
+            GenericArray::from_slice(&nonce_prefix),
+        );
+
+        // the chunks are encrypted while the wrapped location stores them,
+        // an error of the reader is handed over to fail the item
+        let (mut send, recv) = futures::channel::mpsc::channel::<std::io::Result<bytes::Bytes>>(4);
+        let write =
+            self.inner
+                .create_item(container, item, tokio_util::io::StreamReader::new(recv));
+
+        let encrypt = async move {
+            if send.send(Ok(header.clone().into())).await.is_err() {
+                return;
+            }
+
+            // a chunk is only known to be the last one, when the following one is empty
+            let mut chunk = read_chunk(&mut reader).await;
+            loop {
+                let current = match chunk {
+                    Ok(current) => current,
+                    Err(e) => {
+                        let _ = send.send(Err(e)).await;
+                        return;
+                    }
+                };
+
+                let next = if current.len() < CHUNK_SIZE {
+                    Ok(vec![])
+                } else {
+                    read_chunk(&mut reader).await
+                };
+
+                let payload = Payload {
+                    msg: &current,
+                    aad: &header,
+                };
+                if matches!(&next, Ok(next) if next.is_empty()) {
+                    let res = encryptor
+                        .encrypt_last(payload)
+                        .map(bytes::Bytes::from)
+                        .map_err(|_| invalid_data("encryption failed"));
+                    let _ = send.send(res).await;
+                    return;
+                }
+
+                let res = encryptor
+                    .encrypt_next(payload)
+                    .map(bytes::Bytes::from)
+                    .map_err(|_| invalid_data("encryption failed"));
+                let failed = res.is_err();
+                if send.send(res).await.is_err() || failed {
+                    return;
+                }
+                chunk = next;
+            }
+        };
+
+        let (res, _) = futures::join!(write, encrypt);
+        res
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        use futures::SinkExt;
+        use tokio::io::AsyncReadExt;
+
+        let mut reader = self.inner.read_item(container, item).await?;
+        let (id, nonce_prefix, header) = read_header(&mut reader).await?;
+        let mut decryptor = DecryptorBE32::from_aead(
+            self.keys.cipher(&id)?,
+            GenericArray::from_slice(&nonce_prefix),
+        );
+
+        // the chunks are decrypted by a task, which forwards their data
+        let (mut send, recv) = futures::channel::mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let size = CHUNK_SIZE + TAG_SIZE;
+            let mut buf = Vec::with_capacity(size + 1);
+
+            loop {
+                // read one byte more than a chunk, to know if it is the last one
+                let wanted = size + 1 - buf.len();
+                if let Err(e) = (&mut reader)
+                    .take(wanted as u64)
+                    .read_to_end(&mut buf)
+                    .await
+                {
+                    let _ = send.send(Err(e)).await;
+                    return;
+                }
+
+                if buf.len() <= size {
+                    let res = decryptor.decrypt_last(Payload {
+                        msg: &buf,
+                        aad: &header,
+                    });
+                    let res = res
+                        .map(bytes::Bytes::from)
+                        .map_err(|_| invalid_data("decryption failed"));
+                    let _ = send.send(res).await;
+                    return;
+                }
+
+                let rest = buf.split_off(size);
+                let res = decryptor
+                    .decrypt_next(Payload {
+                        msg: &buf,
+                        aad: &header,
+                    })
+                    .map(bytes::Bytes::from)
+                    .map_err(|_| invalid_data("decryption failed"));
+                buf = rest;
+
+                // stop on errors or when the reader got dropped
+                let failed = res.is_err();
+                if send.send(res).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        Ok(Box::new(tokio_util::io::StreamReader::new(recv)))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        self.inner.remove_item(container, item).await
+    }
+}
@@ -5,39 +5,111 @@ pub enum StowError {
     #[error("IO operation failed")]
     Disconnect(#[from] std::io::Error),
 
-    #[error("Google cloud error")]
-    GoogleCloudError(#[from] google_cloud::error::Error),
+    #[cfg(feature = "gcs")]
+    #[error("Signing the GCS token request failed")]
+    JwtError(#[from] jsonwebtoken::errors::Error),
 
     #[error("Environment variable missing")]
     EnvironmentVariable(#[from] std::env::VarError),
 
+    #[cfg(feature = "s3")]
     #[error("Create Bucket on S3 error")]
     RusotoCreateBucketError(#[from] rusoto_core::RusotoError<rusoto_s3::CreateBucketError>),
 
+    #[cfg(feature = "s3")]
     #[error("Region is invalid")]
     RusotoParseRegionError(#[from] rusoto_signature::region::ParseRegionError),
 
+    #[cfg(feature = "s3")]
     #[error("Rusoto TLS error")]
     RusotoTlsError(#[from] rusoto_core::request::TlsError),
 
+    #[cfg(feature = "s3")]
     #[error("Rusoto list bucket error")]
     RusotoListBucketError(#[from] rusoto_core::RusotoError<rusoto_s3::ListBucketsError>),
 
+    #[cfg(feature = "s3")]
     #[error("Rusoto put object error")]
     RusotoPutObjectError(#[from] rusoto_core::RusotoError<rusoto_s3::PutObjectError>),
 
+    #[cfg(feature = "s3")]
     #[error("Rusoto list objects error")]
     RusotoListObjectsError(#[from] rusoto_core::RusotoError<rusoto_s3::ListObjectsV2Error>),
 
+    #[cfg(feature = "s3")]
     #[error("Rusoto get object error")]
     RusotoGetObjectError(#[from] rusoto_core::RusotoError<rusoto_s3::GetObjectError>),
 
+    #[cfg(feature = "s3")]
     #[error("Rusoto delete object error")]
     RusotoDeleteObjectError(#[from] rusoto_core::RusotoError<rusoto_s3::DeleteObjectError>),
 
+    #[cfg(feature = "s3")]
     #[error("Rusoto delete bucket error")]
     RusotoDeleteBucketError(#[from] rusoto_core::RusotoError<rusoto_s3::DeleteBucketError>),
 
+    #[cfg(feature = "reqwest")]
+    #[error("HTTP request failed")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("HTTP request failed with status {0}")]
+    HttpStatusError(u16),
+
+    #[cfg(feature = "serde_json")]
+    #[error("JSON (de)serialization failed")]
+    JsonError(#[from] serde_json::Error),
+
+    #[cfg(feature = "url")]
+    #[error("Url is invalid")]
+    UrlError(#[from] url::ParseError),
+
+    #[cfg(feature = "reqwest")]
+    #[error("HTTP header value is invalid")]
+    HeaderError(#[from] reqwest::header::InvalidHeaderValue),
+
+    #[cfg(any(feature = "azure", feature = "s3"))]
+    #[error("Base64 decoding failed")]
+    Base64Error(#[from] base64::DecodeError),
+
+    #[cfg(feature = "quick-xml")]
+    #[error("XML parsing failed")]
+    XmlError(#[from] quick_xml::Error),
+
+    #[cfg(feature = "swift")]
+    #[error("No Swift object store found in the Keystone catalog")]
+    SwiftCatalogError,
+
+    #[cfg(feature = "b2")]
+    #[error("Unexpected response of the B2 API")]
+    B2ResponseError,
+
+    #[cfg(feature = "gcs")]
+    #[error("The GCS service account credentials are invalid")]
+    GcsCredentialsError,
+
+    #[cfg(feature = "throttle")]
+    #[error("The rate of {0} per second is invalid, it must be positive")]
+    InvalidRateError(f64),
+
+    #[cfg(feature = "sftp")]
+    #[error("SSH operation failed")]
+    SshError(#[from] ssh2::Error),
+
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite operation failed")]
+    SqliteError(#[from] rusqlite::Error),
+
+    #[cfg(feature = "ftp")]
+    #[error("FTP command failed with {0}: {1}")]
+    FtpError(u32, String),
+
+    #[cfg(feature = "ftp")]
+    #[error("Invalid TLS server name")]
+    TlsError(#[from] tokio_rustls::rustls::client::InvalidDnsNameError),
+
+    #[error("Blocking task failed")]
+    TaskError(#[from] tokio::task::JoinError),
+
     #[error("Rusoto empty item error")]
     EmptyItemError,
 
@@ -50,6 +122,92 @@ pub enum StowError {
     #[error("Listing of the containers failed")]
     ListContainerError,
 
+    #[error("The name {0} is not allowed")]
+    InvalidNameError(String),
+
+    #[error("The key {0} is not available")]
+    UnknownKeyError(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("The operation is not supported by this location")]
+    Unsupported,
+
     #[error("Unknown stow error")]
     Unknown,
 }
+
+impl StowError {
+    /// Short name of the kind of the error, e.g. as metrics label
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StowError::Disconnect(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => "not_found",
+                std::io::ErrorKind::PermissionDenied => "permission_denied",
+                std::io::ErrorKind::TimedOut => "timed_out",
+                _ => "io",
+            },
+            #[cfg(feature = "gcs")]
+            StowError::JwtError(_) => "jwt",
+            StowError::EnvironmentVariable(_) => "environment_variable",
+            #[cfg(feature = "s3")]
+            StowError::RusotoCreateBucketError(_) => "s3",
+            #[cfg(feature = "s3")]
+            StowError::RusotoParseRegionError(_) => "s3",
+            #[cfg(feature = "s3")]
+            StowError::RusotoTlsError(_) => "s3",
+            #[cfg(feature = "s3")]
+            StowError::RusotoListBucketError(_) => "s3",
+            #[cfg(feature = "s3")]
+            StowError::RusotoPutObjectError(_) => "s3",
+            #[cfg(feature = "s3")]
+            StowError::RusotoListObjectsError(_) => "s3",
+            #[cfg(feature = "s3")]
+            StowError::RusotoGetObjectError(_) => "s3",
+            #[cfg(feature = "s3")]
+            StowError::RusotoDeleteObjectError(_) => "s3",
+            #[cfg(feature = "s3")]
+            StowError::RusotoDeleteBucketError(_) => "s3",
+            #[cfg(feature = "reqwest")]
+            StowError::HttpError(_) => "http",
+            StowError::HttpStatusError(_) => "http_status",
+            #[cfg(feature = "serde_json")]
+            StowError::JsonError(_) => "json",
+            #[cfg(feature = "url")]
+            StowError::UrlError(_) => "url",
+            #[cfg(feature = "reqwest")]
+            StowError::HeaderError(_) => "http_header",
+            #[cfg(any(feature = "azure", feature = "s3"))]
+            StowError::Base64Error(_) => "base64",
+            #[cfg(feature = "quick-xml")]
+            StowError::XmlError(_) => "xml",
+            #[cfg(feature = "swift")]
+            StowError::SwiftCatalogError => "swift_catalog",
+            #[cfg(feature = "b2")]
+            StowError::B2ResponseError => "b2_response",
+            #[cfg(feature = "gcs")]
+            StowError::GcsCredentialsError => "gcs_credentials",
+            #[cfg(feature = "throttle")]
+            StowError::InvalidRateError(_) => "invalid_rate",
+            #[cfg(feature = "sftp")]
+            StowError::SshError(_) => "ssh",
+            #[cfg(feature = "sqlite")]
+            StowError::SqliteError(_) => "sqlite",
+            #[cfg(feature = "ftp")]
+            StowError::FtpError(..) => "ftp",
+            #[cfg(feature = "ftp")]
+            StowError::TlsError(_) => "tls",
+            StowError::TaskError(_) => "task",
+            StowError::EmptyItemError => "empty_item",
+            StowError::ItemTypMissing => "item_type_missing",
+            StowError::ContainerCreationError => "container_creation",
+            StowError::ListContainerError => "list_container",
+            StowError::InvalidNameError(_) => "invalid_name",
+            StowError::UnknownKeyError(_) => "unknown_key",
+            StowError::PermissionDenied(_) => "permission_denied",
+            StowError::Unsupported => "unsupported",
+            StowError::Unknown => "unknown",
+        }
+    }
+}
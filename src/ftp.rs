@@ -0,0 +1,478 @@
+use crate::*;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+/// Stream of a control or data connection, which is either plain or TLS encrypted
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync> Stream for T {}
+
+/// Location on a FTP server, optionally secured with explicit TLS (FTPS).
+/// Containers are the directories under the root, items are the files in them.
+///
+/// FTP sessions can only run one command at a time,
+/// so every operation uses its own connection to allow concurrent use.
+#[derive(Clone)]
+pub struct Ftp {
+    address: String,
+    user: String,
+    password: String,
+    root: String,
+    /// Shared by all connections, so data connections resume the TLS session
+    /// of their control connection, which servers like vsftpd require by default
+    tls: Option<tokio_rustls::TlsConnector>,
+}
+
+impl Ftp {
+    /// Connect to the FTP server at the given address, e.g. `example.com:21`.
+    /// The root directory gets created if it is not available.
+    pub async fn new(
+        address: &str,
+        user: &str,
+        password: &str,
+        root: &str,
+        tls: bool,
+    ) -> Result<Self> {
+        let this = Self {
+            address: address.to_string(),
+            user: user.to_string(),
+            password: password.to_string(),
+            root: match root.trim_end_matches('/') {
+                "" => "/".to_string(),
+                root => root.to_string(),
+            },
+            tls: match tls {
+                true => Some(tls_connector()?),
+                false => None,
+            },
+        };
+
+        // check the login and create the root
+        let mut con = this.connect_without_root().await?;
+        if con.command(&format!("CWD {}", this.root)).await?.0 != 250 {
+            con.expect(&format!("MKD {}", this.root), &[257]).await?;
+        }
+
+        Ok(this)
+    }
+
+    /// Open a new logged in connection, without changing into the root
+    async fn connect_without_root(&self) -> Result<Connection> {
+        let tcp = tokio::net::TcpStream::connect(&self.address).await?;
+        let host = tcp_host(&self.address);
+
+        let mut con = Connection::new(Box::new(tcp), &host, None);
+        con.expect_response(&[220]).await?;
+
+        // upgrade the control connection to TLS
+        if let Some(tls) = &self.tls {
+            con.expect("AUTH TLS", &[234]).await?;
+
+            let stream = tls
+                .connect(server_name(&host)?, con.control.into_inner())
+                .await?;
+            con = Connection::new(Box::new(stream), &host, Some(tls.clone()));
+
+            con.expect("PBSZ 0", &[200]).await?;
+            con.expect("PROT P", &[200]).await?;
+        }
+
+        if con.command(&format!("USER {}", self.user)).await?.0 == 331 {
+            con.expect(&format!("PASS {}", self.password), &[230, 202])
+                .await?;
+        }
+        con.expect("TYPE I", &[200]).await?;
+
+        Ok(con)
+    }
+
+    /// Open a new logged in connection, with the root as working directory
+    async fn connect(&self) -> Result<Connection> {
+        let mut con = self.connect_without_root().await?;
+        con.expect(&format!("CWD {}", self.root), &[250]).await?;
+        Ok(con)
+    }
+
+    /// List the names of the directories or files in the given directory
+    async fn list(&self, path: &str, dirs: bool) -> Result<Vec<String>> {
+        use tokio::io::AsyncReadExt;
+
+        let mut con = self.connect().await?;
+
+        // prefer the machine readable listing, not all servers support it
+        let (mlsd, mut data) = match con.transfer(&format!("MLSD {}", path)).await {
+            Ok(data) => (true, data),
+            Err(StowError::FtpError(code, _)) if code >= 500 => {
+                (false, con.transfer(&format!("LIST {}", path)).await?)
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut listing = String::new();
+        data.read_to_string(&mut listing).await?;
+        drop(data);
+        con.expect_response(&[226, 250]).await?;
+
+        Ok(listing
+            .lines()
+            .filter_map(|l| if mlsd { parse_mlsd(l) } else { parse_list(l) })
+            .filter(|(_, dir)| *dir == dirs)
+            .map(|(name, _)| name)
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Ftp {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.list(".", true).await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        let mut con = self.connect().await?;
+
+        // only create the directory if not available
+        if con.command(&format!("CWD {}", container)).await?.0 != 250 {
+            con.expect(&format!("MKD {}", container), &[257]).await?;
+        }
+        Ok(())
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        let container = util::streamline(container);
+
+        // directories need to be empty to be removed
+        let items = self.list(&container, false).await?;
+
+        let mut con = self.connect().await?;
+        for item in items {
+            con.expect(&format!("DELE {}/{}", container, item), &[250])
+                .await?;
+        }
+        con.expect(&format!("RMD {}", container), &[250]).await?;
+        Ok(())
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let container = util::streamline(container);
+
+        self.list(&container, false).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        let item = util::streamline_item(item)?;
+
+        let mut con = self.connect().await?;
+        let mut data = con
+            .transfer(&format!("STOR {}/{}", container, item))
+            .await?;
+        tokio::io::copy(&mut reader, &mut data).await?;
+        data.shutdown().await?;
+        drop(data);
+
+        con.expect_response(&[226, 250]).await?;
+        Ok(())
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let mut con = self.connect().await?;
+        let data = con
+            .transfer(&format!("RETR {}/{}", container, item))
+            .await?;
+
+        Ok(Box::new(FtpReader { data, _con: con }))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let mut con = self.connect().await?;
+        con.expect(&format!("DELE {}/{}", container, item), &[250])
+            .await?;
+        Ok(())
+    }
+}
+
+/// Control connection of a FTP session
+struct Connection {
+    control: tokio::io::BufReader<Box<dyn Stream>>,
+    /// The host of the server, passive data connections are opened to it
+    host: String,
+    tls: Option<tokio_rustls::TlsConnector>,
+}
+
+impl Connection {
+    fn new(stream: Box<dyn Stream>, host: &str, tls: Option<tokio_rustls::TlsConnector>) -> Self {
+        Self {
+            control: tokio::io::BufReader::new(stream),
+            host: host.to_string(),
+            tls,
+        }
+    }
+
+    /// Send a command and read the response
+    async fn command(&mut self, command: &str) -> Result<(u32, String)> {
+        let stream = self.control.get_mut();
+        stream.write_all(command.as_bytes()).await?;
+        stream.write_all(b"\r\n").await?;
+        stream.flush().await?;
+
+        self.response().await
+    }
+
+    /// Read a response, which can span multiple lines
+    async fn response(&mut self) -> Result<(u32, String)> {
+        let mut message = String::new();
+
+        loop {
+            let mut line = String::new();
+            if self.control.read_line(&mut line).await? == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            message.push_str(&line);
+
+            // the last line starts with the code followed by a space
+            let code = line.get(0..3).and_then(|c| c.parse::<u32>().ok());
+            if let (Some(code), Some(b' ')) = (code, line.as_bytes().get(3)) {
+                return Ok((code, message.trim_end().to_string()));
+            }
+        }
+    }
+
+    /// Send a command and fail if the response has none of the expected codes
+    async fn expect(&mut self, command: &str, codes: &[u32]) -> Result<String> {
+        let (code, message) = self.command(command).await?;
+        check(code, message, codes)
+    }
+
+    /// Read a response and fail if it has none of the expected codes
+    async fn expect_response(&mut self, codes: &[u32]) -> Result<String> {
+        let (code, message) = self.response().await?;
+        check(code, message, codes)
+    }
+
+    /// Open a passive data connection and start the transfer of the command on it
+    async fn transfer(&mut self, command: &str) -> Result<Box<dyn Stream>> {
+        // EPSV works with IPv4 and IPv6, PASV is the fallback for older servers
+        let port = match self.command("EPSV").await? {
+            (229, message) => parse_epsv(&message).ok_or(StowError::FtpError(229, message))?,
+            _ => {
+                let message = self.expect("PASV", &[227]).await?;
+                parse_pasv(&message).ok_or(StowError::FtpError(227, message))?
+            }
+        };
+
+        let data = tokio::net::TcpStream::connect((self.host.as_str(), port)).await?;
+        self.expect(command, &[125, 150]).await?;
+
+        // the data connection gets secured after the server accepted the transfer
+        Ok(match &self.tls {
+            Some(tls) => Box::new(tls.connect(server_name(&self.host)?, data).await?),
+            None => Box::new(data),
+        })
+    }
+}
+
+/// Reader of an item, which keeps the FTP session alive until it is dropped
+struct FtpReader {
+    data: Box<dyn Stream>,
+    _con: Connection,
+}
+
+impl AsyncRead for FtpReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.data).poll_read(cx, buf)
+    }
+}
+
+fn check(code: u32, message: String, codes: &[u32]) -> Result<String> {
+    if !codes.contains(&code) {
+        return Err(StowError::FtpError(code, message));
+    }
+    Ok(message)
+}
+
+/// Create the TLS configuration, which trusts the root certificates of the system
+fn tls_connector() -> Result<tokio_rustls::TlsConnector> {
+    use tokio_rustls::rustls;
+
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in rustls_native_certs::load_native_certs()? {
+        // certificates which can't be parsed are skipped
+        let _ = roots.add(&rustls::Certificate(cert.0));
+    }
+
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(tokio_rustls::TlsConnector::from(Arc::new(config)))
+}
+
+/// Get the name of the host the TLS certificate is checked against
+fn server_name(host: &str) -> Result<tokio_rustls::rustls::ServerName> {
+    use std::convert::TryFrom;
+
+    Ok(tokio_rustls::rustls::ServerName::try_from(host)?)
+}
+
+/// Get the host of an address with a port
+fn tcp_host(address: &str) -> String {
+    address
+        .rsplit_once(':')
+        .map_or(address, |(h, _)| h)
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string()
+}
+
+/// Parse the port of a PASV response like `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)`.
+/// The host is ignored, because servers behind a NAT often report an internal one
+fn parse_pasv(message: &str) -> Option<u16> {
+    let (_, address) = message.rsplit_once('(')?;
+    let numbers = address
+        .split(')')
+        .next()?
+        .split(',')
+        .map(|n| n.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    match numbers[..] {
+        [_, _, _, _, p1, p2] => Some(u16::from(p1) << 8 | u16::from(p2)),
+        _ => None,
+    }
+}
+
+/// Parse the port of an EPSV response like `229 Entering Extended Passive Mode (|||6446|)`
+fn parse_epsv(message: &str) -> Option<u16> {
+    let (_, address) = message.rsplit_once('(')?;
+    let address = address.split(')').next()?;
+
+    // the first character is the delimiter of the fields, the port is the third one
+    let delimiter = address.chars().next()?;
+    address.split(delimiter).nth(3)?.parse().ok()
+}
+
+/// Parse a MLSD line like `type=file;size=12;modify=20210101000000; name.txt`
+fn parse_mlsd(line: &str) -> Option<(String, bool)> {
+    let (facts, name) = line.split_once(' ')?;
+
+    let typ = facts
+        .split(';')
+        .filter_map(|f| f.split_once('='))
+        .find(|(k, _)| k.eq_ignore_ascii_case("type"))?
+        .1
+        .to_lowercase();
+
+    match typ.as_str() {
+        "file" => Some((name.to_string(), false)),
+        "dir" => Some((name.to_string(), true)),
+        // the current and parent directory
+        _ => None,
+    }
+}
+
+/// Parse a unix style LIST line like `drwxr-xr-x 2 user group 4096 Jan 1 00:00 name`
+fn parse_list(line: &str) -> Option<(String, bool)> {
+    let mode = line.split_whitespace().next()?;
+    let name = line
+        .split_whitespace()
+        .skip(8)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+    Some((name, mode.starts_with('d')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pasv() {
+        assert_eq!(
+            parse_pasv("227 Entering Passive Mode (192,168,1,2,19,137)"),
+            Some(5001)
+        );
+        assert_eq!(
+            parse_pasv("227 Entering Passive Mode (10,0,0,1,255,255)"),
+            Some(65535)
+        );
+        assert_eq!(
+            parse_pasv("227 Entering Passive Mode (10,0,0,1,300,1)"),
+            None
+        );
+        assert_eq!(parse_pasv("227 Entering Passive Mode (10,0,0,1,19)"), None);
+        assert_eq!(parse_pasv("227 Entering Passive Mode"), None);
+    }
+
+    #[test]
+    fn test_parse_epsv() {
+        assert_eq!(
+            parse_epsv("229 Entering Extended Passive Mode (|||6446|)"),
+            Some(6446)
+        );
+        assert_eq!(
+            parse_epsv("229 Extended Passive Mode OK (!!!21000!)"),
+            Some(21000)
+        );
+        assert_eq!(
+            parse_epsv("229 Entering Extended Passive Mode (|||70000|)"),
+            None
+        );
+        assert_eq!(parse_epsv("229 Entering Extended Passive Mode"), None);
+    }
+
+    #[test]
+    fn test_parse_mlsd() {
+        assert_eq!(
+            parse_mlsd("type=file;size=12;modify=20210101000000; test file.txt"),
+            Some(("test file.txt".to_string(), false))
+        );
+        assert_eq!(
+            parse_mlsd("Type=DIR;modify=20210101000000; container-1"),
+            Some(("container-1".to_string(), true))
+        );
+        assert_eq!(parse_mlsd("type=cdir;modify=20210101000000; ."), None);
+        assert_eq!(parse_mlsd("type=pdir;modify=20210101000000; .."), None);
+        assert_eq!(parse_mlsd("size=12"), None);
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            parse_list("-rw-r--r--    1 user     group          12 Jan 01 00:00 test file.txt"),
+            Some(("test file.txt".to_string(), false))
+        );
+        assert_eq!(
+            parse_list("drwxr-xr-x    2 user     group        4096 Jan 01 00:00 container-1"),
+            Some(("container-1".to_string(), true))
+        );
+        assert_eq!(
+            parse_list("drwxr-xr-x    2 user     group        4096 Jan 01 00:00 .."),
+            None
+        );
+        assert_eq!(parse_list("total 8"), None);
+    }
+}
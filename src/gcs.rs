@@ -1,102 +1,319 @@
 use crate::*;
+use std::sync::Arc;
 
+/// Default endpoint of the Cloud Storage JSON API
+const ENDPOINT: &str = "https://storage.googleapis.com";
+
+/// Scope of the requested access tokens
+const SCOPE: &str = "https://www.googleapis.com/auth/devstorage.full_control";
+
+/// Credentials of a service account, read from its json key file
+struct ServiceAccount {
+    client_email: String,
+    private_key: jsonwebtoken::EncodingKey,
+    token_uri: String,
+}
+
+/// Access token of the service account
+struct Token {
+    value: String,
+    expires: std::time::Instant,
+}
+
+/// Location on Google Cloud Storage, using the JSON API.
+/// Containers are buckets, items are objects.
 #[derive(Clone)]
 pub struct Gcs {
-    client: google_cloud::storage::Client,
+    client: reqwest::Client,
+    project: String,
+    endpoint: String,
+    account: Option<Arc<ServiceAccount>>,
+    token: Arc<tokio::sync::RwLock<Option<Token>>>,
+    kms_key: Option<String>,
 }
 
 impl Gcs {
+    /// Authenticate with the service account, whose json key file is stored at the path
     pub async fn new(project_name: &str, path: &str) -> Result<Self> {
-        std::env::set_var("GOOGLE_APPLICATION_CREDENTIALS", path);
+        let key: serde_json::Value = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+        let field = |name: &str| {
+            key[name]
+                .as_str()
+                .map(|s| s.to_string())
+                .ok_or(StowError::GcsCredentialsError)
+        };
+
+        let account = ServiceAccount {
+            client_email: field("client_email")?,
+            private_key: jsonwebtoken::EncodingKey::from_rsa_pem(field("private_key")?.as_bytes())?,
+            token_uri: field("token_uri")
+                .unwrap_or_else(|_| "https://oauth2.googleapis.com/token".to_string()),
+        };
 
-        Ok(Self {
-            client: google_cloud::storage::Client::new(project_name).await?,
-        })
+        let this = Self {
+            account: Some(Arc::new(account)),
+            ..Self::new_anonymous(project_name)
+        };
+
+        // check the credentials
+        this.token().await?;
+        Ok(this)
     }
-}
 
-#[async_trait::async_trait]
-impl Adapter for Gcs {
-    async fn containers(&mut self) -> Result<Vec<String>> {
-        Ok(self
-            .client
-            .buckets()
-            .await?
-            .into_iter()
-            .map(|b| b.name().to_string())
-            .collect())
+    /// Send the requests without authentication, e.g. for public buckets or an emulator
+    pub fn new_anonymous(project_name: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            project: project_name.to_string(),
+            endpoint: ENDPOINT.to_string(),
+            account: None,
+            token: Arc::new(tokio::sync::RwLock::new(None)),
+            kms_key: None,
+        }
     }
 
-    async fn create_container(&mut self, container: &str) -> Result<()> {
-        // only create bucket if not avialble
-        if self.client.bucket(&container).await.is_err() {
-            self.client
-                .create_bucket(&util::streamline(&container))
-                .await?;
+    /// Use a custom endpoint instead of the default Google one.
+    /// This is needed for an emulator, e.g. `http://localhost:4443` for fake-gcs-server
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Encrypt created items with the Cloud KMS key by default, instead of the default key of the bucket.
+    /// The key name has the form `projects/{project}/locations/{location}/keyRings/{ring}/cryptoKeys/{key}`
+    pub fn with_kms_key(mut self, key_name: &str) -> Self {
+        self.kms_key = Some(key_name.to_string());
+        self
+    }
+
+    /// Get a valid access token, a new one is requested shortly before the current one expires
+    async fn token(&self) -> Result<Option<String>> {
+        let account = match &self.account {
+            Some(account) => account,
+            None => return Ok(None),
+        };
+        let valid = |token: &Option<Token>| match token {
+            Some(t)
+                if t.expires > std::time::Instant::now() + std::time::Duration::from_secs(60) =>
+            {
+                Some(t.value.clone())
+            }
+            _ => None,
         };
 
-        Ok(())
+        if let Some(token) = valid(&*self.token.read().await) {
+            return Ok(Some(token));
+        }
+
+        // only request a new token, if no other task did it in the meantime
+        let mut token = self.token.write().await;
+        if let Some(token) = valid(&token) {
+            return Ok(Some(token));
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|_| StowError::Unknown)?
+            .as_secs();
+        let claims = serde_json::json!({
+            "iss": account.client_email,
+            "scope": SCOPE,
+            "aud": account.token_uri,
+            "iat": now,
+            "exp": now + 3600,
+        });
+        let assertion = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+            &claims,
+            &account.private_key,
+        )?;
+
+        let res = self
+            .client
+            .post(&account.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", &assertion),
+            ])
+            .send()
+            .await?;
+        let res: serde_json::Value = util::http_status(res)?.json().await?;
+
+        let value = res["access_token"]
+            .as_str()
+            .ok_or(StowError::GcsCredentialsError)?
+            .to_string();
+        let expires = std::time::Instant::now()
+            + std::time::Duration::from_secs(res["expires_in"].as_u64().unwrap_or(3600));
+
+        *token = Some(Token {
+            value: value.clone(),
+            expires,
+        });
+        Ok(Some(value))
     }
 
-    async fn remove_container(&mut self, container: &str) -> Result<()> {
-        let bucket = self.client.bucket(&util::streamline(container)).await?;
-        bucket.delete().await?;
-        Ok(())
+    /// Create the url of the given path segments, relative to the endpoint
+    fn url(&self, path: &[&str]) -> Result<reqwest::Url> {
+        let mut url = reqwest::Url::parse(&self.endpoint)?;
+        url.path_segments_mut()
+            .map_err(|_| StowError::Unknown)?
+            .pop_if_empty()
+            .extend(path);
+        Ok(url)
     }
 
-    async fn items(&mut self, container: &str) -> Result<Vec<String>> {
-        let mut bucket = self.client.bucket(&util::streamline(container)).await?;
-        Ok(bucket
-            .objects()
-            .await?
-            .into_iter()
-            .map(|o| o.name().to_string())
-            .collect())
+    /// Send the request with the access token
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let request = match self.token().await? {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        util::http_status(request.send().await?)
     }
 
-    async fn create_item(
-        &mut self,
+    /// Request a paged listing and collect the names of all entries
+    async fn list(&self, url: reqwest::Url, query: &[(&str, &str)]) -> Result<Vec<String>> {
+        let mut names = vec![];
+        let mut page: Option<String> = None;
+
+        loop {
+            let mut request = self.client.get(url.clone()).query(query);
+            if let Some(page) = &page {
+                request = request.query(&[("pageToken", page)]);
+            }
+            let res: serde_json::Value = self.send(request).await?.json().await?;
+
+            names.extend(
+                res["items"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|i| i["name"].as_str())
+                    .map(|n| n.to_string()),
+            );
+
+            match res["nextPageToken"].as_str() {
+                Some(token) => page = Some(token.to_string()),
+                None => return Ok(names),
+            }
+        }
+    }
+
+    /// Get the ETag of the object, without reading it
+    pub async fn etag(&self, container: &str, item: &str) -> Result<Option<String>> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let url = self.url(&["storage", "v1", "b", &container, "o", &item])?;
+        let res: serde_json::Value = self.send(self.client.get(url)).await?.json().await?;
+        Ok(res["etag"].as_str().map(|e| e.to_string()))
+    }
+
+    /// Create the item encrypted with the Cloud KMS key, instead of the default one
+    pub async fn create_item_with_kms_key(
+        &self,
         container: &str,
         item: &str,
         mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+        kms_key: Option<&str>,
     ) -> Result<()> {
         use tokio::io::AsyncReadExt;
 
         let item = util::streamline_item(item)?;
 
-        let mut bucket = self.client.bucket(&container).await?;
-
         let mut data = vec![];
         reader.read_to_end(&mut data).await?;
 
-        bucket
-            .create_object(&item, data, "application/octet-stream")
+        let url = self.url(&["upload", "storage", "v1", "b", container, "o"])?;
+        let mut request = self
+            .client
+            .post(url)
+            .query(&[("uploadType", "media"), ("name", &item)]);
+        if let Some(kms_key) = kms_key {
+            request = request.query(&[("kmsKeyName", kms_key)]);
+        }
+
+        self.send(
+            request
+                .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                .body(data),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Gcs {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.list(
+            self.url(&["storage", "v1", "b"])?,
+            &[("project", &self.project)],
+        )
+        .await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        // only create bucket if not avialble
+        let url = self.url(&["storage", "v1", "b", container])?;
+        if self.send(self.client.get(url)).await.is_err() {
+            let url = self.url(&["storage", "v1", "b"])?;
+            self.send(
+                self.client
+                    .post(url)
+                    .query(&[("project", &self.project)])
+                    .json(&serde_json::json!({ "name": util::streamline(container) })),
+            )
             .await?;
+        };
 
         Ok(())
     }
 
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        let url = self.url(&["storage", "v1", "b", &util::streamline(container)])?;
+        self.send(self.client.delete(url)).await?;
+        Ok(())
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let url = self.url(&["storage", "v1", "b", &util::streamline(container), "o"])?;
+        self.list(url, &[]).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        self.create_item_with_kms_key(container, item, reader, self.kms_key.as_deref())
+            .await
+    }
+
     async fn read_item(
-        &mut self,
+        &self,
         container: &str,
         item: &str,
     ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
         let container = util::streamline(container);
         let item = util::streamline_item(item)?;
 
-        let mut bucket = self.client.bucket(&container).await?;
-        let mut object = bucket.object(&item).await?;
-
-        Ok(Box::new(object.reader().await?))
+        let url = self.url(&["storage", "v1", "b", &container, "o", &item])?;
+        let res = self
+            .send(self.client.get(url).query(&[("alt", "media")]))
+            .await?;
+        Ok(util::response_reader(res))
     }
 
-    async fn remove_item(&mut self, container: &str, item: &str) -> Result<()> {
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
         let container = util::streamline(container);
         let item = util::streamline_item(item)?;
 
-        let mut bucket = self.client.bucket(&container).await?;
-        let object = bucket.object(&item).await?;
-        object.delete().await?;
+        let url = self.url(&["storage", "v1", "b", &container, "o", &item])?;
+        self.send(self.client.delete(url)).await?;
         Ok(())
     }
 }
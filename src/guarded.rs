@@ -0,0 +1,174 @@
+use crate::*;
+use std::sync::Arc;
+
+/// Which operations a guarded location allows, everything is allowed by default
+#[derive(Debug, Clone)]
+pub struct GuardPolicy {
+    /// Refuse all operations, which change containers or items
+    pub read_only: bool,
+    /// Allow to create and remove containers
+    pub manage_containers: bool,
+    /// Only these containers can be accessed, all of them if none are given
+    pub containers: Option<Vec<String>>,
+    /// Maximum size of created items in bytes
+    pub max_item_size: Option<u64>,
+}
+
+impl Default for GuardPolicy {
+    fn default() -> Self {
+        Self {
+            read_only: false,
+            manage_containers: true,
+            containers: None,
+            max_item_size: None,
+        }
+    }
+}
+
+impl GuardPolicy {
+    /// Policy which only allows to list and read
+    pub fn read_only() -> Self {
+        Self {
+            read_only: true,
+            manage_containers: false,
+            ..Default::default()
+        }
+    }
+}
+
+/// Location wrapper, which refuses operations the policy doesn't allow with `StowError::PermissionDenied`,
+/// e.g. for handles given to plugins.
+///
+/// With a maximum item size, created items are buffered in memory up to it,
+/// so an existing item is not overwritten by a refused one.
+#[derive(Clone)]
+pub struct Guarded {
+    pub(crate) inner: Arc<Location>,
+    policy: Arc<GuardPolicy>,
+}
+
+impl Guarded {
+    /// Wrap the location, to only allow the operations of the policy
+    pub fn new(inner: Location, policy: GuardPolicy) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            policy: Arc::new(policy),
+        }
+    }
+
+    /// Check if the container can be accessed
+    fn check_container(&self, container: &str) -> Result<()> {
+        match &self.policy.containers {
+            Some(containers)
+                if !containers
+                    .iter()
+                    .any(|c| util::streamline(c) == util::streamline(container)) =>
+            {
+                Err(StowError::PermissionDenied(format!(
+                    "access to container {}",
+                    container
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check if the operation changes something and the location is read-only
+    fn check_write(&self, operation: &str) -> Result<()> {
+        if self.policy.read_only {
+            return Err(StowError::PermissionDenied(format!(
+                "{} on a read-only location",
+                operation
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check if containers can be created or removed
+    fn check_manage(&self, operation: &str, container: &str) -> Result<()> {
+        self.check_write(operation)?;
+        self.check_container(container)?;
+
+        if !self.policy.manage_containers {
+            return Err(StowError::PermissionDenied(format!(
+                "{} of container {}",
+                operation, container
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Guarded {
+    async fn containers(&self) -> Result<Vec<String>> {
+        Ok(self
+            .inner
+            .containers()
+            .await?
+            .into_iter()
+            .filter(|c| self.check_container(c).is_ok())
+            .collect())
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        self.check_manage("creation", container)?;
+        self.inner.create_container(container).await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        self.check_manage("removal", container)?;
+        self.inner.remove_container(container).await
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        self.check_container(container)?;
+        self.inner.items(container).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        reader: impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
+    ) -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        self.check_write("item creation")?;
+        self.check_container(container)?;
+
+        let max = match self.policy.max_item_size {
+            Some(max) => max,
+            None => return self.inner.create_item(container, item, reader).await,
+        };
+
+        // read one byte more than allowed, to know if the item is too large
+        let mut data = vec![];
+        reader.take(max + 1).read_to_end(&mut data).await?;
+        if data.len() as u64 > max {
+            return Err(StowError::PermissionDenied(format!(
+                "item larger than {} bytes",
+                max
+            )));
+        }
+
+        self.inner
+            .create_item(container, item, std::io::Cursor::new(data))
+            .await
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        self.check_container(container)?;
+        self.inner.read_item(container, item).await
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        self.check_write("item removal")?;
+        self.check_container(container)?;
+        self.inner.remove_item(container, item).await
+    }
+}
@@ -0,0 +1,164 @@
+use crate::*;
+
+/// Metadata of an item, as returned by a HEAD request
+#[derive(Debug, Clone, Default)]
+pub struct HttpItemInfo {
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Read-only location on a plain HTTP(S) server.
+/// Items are available at `base_url/container/item`.
+/// Listing is only supported, when an index file is set, which contains one name per line.
+#[derive(Clone)]
+pub struct Http {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    index: Option<String>,
+}
+
+impl Http {
+    pub async fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url: reqwest::Url::parse(url)?,
+            index: None,
+        })
+    }
+
+    /// Use the index file with the given name to list the containers and items,
+    /// e.g. `index.txt` for `base_url/index.txt` and `base_url/container/index.txt`
+    pub fn with_index(mut self, index: &str) -> Self {
+        self.index = Some(index.to_string());
+        self
+    }
+
+    /// Create the url of the given path segments, relative to the base url
+    fn url(&self, path: &[&str]) -> Result<reqwest::Url> {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| StowError::Unknown)?
+            .pop_if_empty()
+            .extend(path);
+        Ok(url)
+    }
+
+    /// Read the index file of the given path
+    async fn list(&self, path: &[&str]) -> Result<Vec<String>> {
+        let index = self.index.as_ref().ok_or(StowError::Unsupported)?;
+
+        let mut path = path.to_vec();
+        path.push(index);
+
+        let res = self.client.get(self.url(&path)?).send().await?;
+        let text = util::http_status(res)?.text().await?;
+
+        Ok(text
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_string())
+            .collect())
+    }
+
+    /// Read only the given byte range of the item
+    pub async fn read_item_range(
+        &self,
+        container: &str,
+        item: &str,
+        range: std::ops::Range<u64>,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        if range.is_empty() {
+            return Ok(Box::new(tokio::io::empty()));
+        }
+
+        let res = self
+            .client
+            .get(self.url(&[container, item])?)
+            .header(
+                reqwest::header::RANGE,
+                format!("bytes={}-{}", range.start, range.end - 1),
+            )
+            .send()
+            .await?;
+        let res = util::http_status(res)?;
+
+        // the server ignored the range and sends the full item
+        if res.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Err(StowError::Unsupported);
+        }
+
+        Ok(util::response_reader(res))
+    }
+
+    /// Get the metadata of the item, without reading it
+    pub async fn stat_item(&self, container: &str, item: &str) -> Result<HttpItemInfo> {
+        let res = self
+            .client
+            .head(self.url(&[container, item])?)
+            .send()
+            .await?;
+        let res = util::http_status(res)?;
+
+        let header = |name| {
+            res.headers()
+                .get(name)
+                .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+
+        Ok(HttpItemInfo {
+            size: header(reqwest::header::CONTENT_LENGTH).and_then(|v| v.parse().ok()),
+            content_type: header(reqwest::header::CONTENT_TYPE),
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Http {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.list(&[]).await
+    }
+
+    async fn create_container(&self, _container: &str) -> Result<()> {
+        Err(StowError::Unsupported)
+    }
+
+    async fn remove_container(&self, _container: &str) -> Result<()> {
+        Err(StowError::Unsupported)
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        self.list(&[container]).await
+    }
+
+    async fn create_item(
+        &self,
+        _container: &str,
+        _item: &str,
+        _reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        Err(StowError::Unsupported)
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let res = self
+            .client
+            .get(self.url(&[container, item])?)
+            .send()
+            .await?;
+        Ok(util::response_reader(util::http_status(res)?))
+    }
+
+    async fn remove_item(&self, _container: &str, _item: &str) -> Result<()> {
+        Err(StowError::Unsupported)
+    }
+}
@@ -1,39 +1,162 @@
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "azure")]
+mod azure;
+#[cfg(feature = "b2")]
+mod b2;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(feature = "compression")]
+mod compressed;
+#[cfg(feature = "dedup")]
+mod dedup;
+#[cfg(feature = "encryption")]
+mod encrypted;
 mod error;
+#[cfg(feature = "ftp")]
+mod ftp;
+#[cfg(feature = "gcs")]
 mod gcs;
+#[cfg(feature = "guard")]
+mod guarded;
+#[cfg(feature = "http")]
+mod http;
 mod local;
+mod memory;
+#[cfg(feature = "metrics")]
+mod metered;
+#[cfg(feature = "prefix")]
+mod prefixed;
+#[cfg(feature = "retry")]
+mod retry;
+#[cfg(feature = "s3")]
 mod s3;
+#[cfg(feature = "sftp")]
+mod sftp;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "swift")]
+mod swift;
+#[cfg(feature = "throttle")]
+mod throttled;
+#[cfg(feature = "webdav")]
+mod webdav;
 
+#[cfg(feature = "archive")]
+pub use archive::*;
+#[cfg(feature = "azure")]
+pub use azure::*;
+#[cfg(feature = "b2")]
+pub use b2::*;
+#[cfg(feature = "cache")]
+pub use cache::*;
+#[cfg(feature = "compression")]
+pub use compressed::*;
+#[cfg(feature = "dedup")]
+pub use dedup::*;
+#[cfg(feature = "encryption")]
+pub use encrypted::*;
 pub use error::*;
+#[cfg(feature = "ftp")]
+pub use ftp::*;
+#[cfg(feature = "gcs")]
 pub use gcs::*;
+#[cfg(feature = "guard")]
+pub use guarded::*;
+#[cfg(feature = "http")]
+pub use http::*;
 pub use local::*;
+pub use memory::*;
+#[cfg(feature = "metrics")]
+pub use metered::*;
+#[cfg(feature = "prefix")]
+pub use prefixed::*;
+#[cfg(feature = "retry")]
+pub use retry::*;
+#[cfg(feature = "s3")]
 pub use s3::*;
+#[cfg(feature = "sftp")]
+pub use sftp::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite::*;
+#[cfg(feature = "swift")]
+pub use swift::*;
+#[cfg(feature = "throttle")]
+pub use throttled::*;
+#[cfg(feature = "webdav")]
+pub use webdav::*;
 
 #[async_trait::async_trait]
-pub trait Adapter: Clone {
-    async fn containers(&mut self) -> Result<Vec<String>>;
-    async fn create_container(&mut self, container: &str) -> Result<()>;
-    async fn remove_container(&mut self, container: &str) -> Result<()>;
+pub trait Adapter: Clone + Send + Sync {
+    async fn containers(&self) -> Result<Vec<String>>;
+    async fn create_container(&self, container: &str) -> Result<()>;
+    async fn remove_container(&self, container: &str) -> Result<()>;
 
-    async fn items(&mut self, container: &str) -> Result<Vec<String>>;
+    async fn items(&self, container: &str) -> Result<Vec<String>>;
     async fn create_item(
-        &mut self,
+        &self,
         container: &str,
         item: &str,
         reader: (impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static),
     ) -> Result<()>;
     async fn read_item(
-        &mut self,
+        &self,
         container: &str,
         item: &str,
     ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>>;
-    async fn remove_item(&mut self, container: &str, item: &str) -> Result<()>;
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()>;
 }
 
+/// A storage location backed by one of the supported adapters.
+///
+/// All operations take `&self`, so a single location can be wrapped
+/// in an `Arc` and used from many tasks at once.
 #[derive(Clone)]
 pub enum Location {
     Local(LocalLocation),
+    Memory(MemoryLocation),
+    #[cfg(feature = "gcs")]
     Gcs(Gcs),
+    #[cfg(feature = "s3")]
     S3(S3),
+    #[cfg(feature = "azure")]
+    Azure(Azure),
+    #[cfg(feature = "sftp")]
+    Sftp(Sftp),
+    #[cfg(feature = "webdav")]
+    WebDav(WebDav),
+    #[cfg(feature = "http")]
+    Http(Http),
+    #[cfg(feature = "ftp")]
+    Ftp(Ftp),
+    #[cfg(feature = "swift")]
+    Swift(Swift),
+    #[cfg(feature = "b2")]
+    B2(B2),
+    #[cfg(feature = "archive")]
+    Archive(Archive),
+    #[cfg(feature = "sqlite")]
+    Sqlite(Sqlite),
+    #[cfg(feature = "dedup")]
+    Dedup(Dedup),
+    #[cfg(feature = "retry")]
+    Retry(Retry),
+    #[cfg(feature = "cache")]
+    Cache(Cache),
+    #[cfg(feature = "metrics")]
+    Metered(Metered),
+    #[cfg(feature = "encryption")]
+    Encrypted(Encrypted),
+    #[cfg(feature = "compression")]
+    Compressed(Compressed),
+    #[cfg(feature = "prefix")]
+    Prefixed(Prefixed),
+    #[cfg(feature = "guard")]
+    Guarded(Guarded),
+    #[cfg(feature = "throttle")]
+    Throttled(Throttled),
 }
 
 impl Location {
@@ -42,95 +165,916 @@ impl Location {
         Ok(Location::Local(LocalLocation::new(path).await?))
     }
 
+    /// Create a new in-memory location, which is empty and
+    /// lives as long as the location or one of its clones
+    pub async fn new_memory() -> Result<Self> {
+        Ok(Location::Memory(MemoryLocation::new().await?))
+    }
+
     /// Create a new gcs location with the given project
     /// The google service account details need to be stored in the json file.
     /// The path to the json file, need to be set as path
+    #[cfg(feature = "gcs")]
     pub async fn new_gcs(project: &str, path: &str) -> Result<Self> {
         Ok(Location::Gcs(Gcs::new(project, path).await?))
     }
 
     /// Create a new S3 location with the given region and credentials
+    #[cfg(feature = "s3")]
     pub async fn new_s3(region: &str, access_key: &str, secret_key: &str) -> Result<Self> {
         Ok(Location::S3(S3::new(region, access_key, secret_key).await?))
     }
 
-    pub async fn containers(&mut self) -> Result<Vec<String>> {
-        match self {
-            Location::Local(l) => l.containers().await,
-            Location::Gcs(l) => l.containers().await,
-            Location::S3(l) => l.containers().await,
-        }
+    /// Create a new Azure blob storage location for the given account,
+    /// authenticated with the base64 encoded access key of the account
+    #[cfg(feature = "azure")]
+    pub async fn new_azure(account: &str, access_key: &str) -> Result<Self> {
+        Ok(Location::Azure(
+            Azure::new(account, AzureAuth::SharedKey(access_key.into())).await?,
+        ))
     }
 
-    pub async fn create_container(&mut self, container: &str) -> Result<()> {
-        let container = util::streamline(&container);
+    /// Create a new Azure blob storage location for the given account,
+    /// authenticated with a shared access signature token
+    #[cfg(feature = "azure")]
+    pub async fn new_azure_sas(account: &str, sas_token: &str) -> Result<Self> {
+        Ok(Location::Azure(
+            Azure::new(account, AzureAuth::Sas(sas_token.into())).await?,
+        ))
+    }
+
+    /// Create a new SFTP location on the server with the given address, e.g. `example.com:22`.
+    /// The containers are stored as directories under the root path.
+    #[cfg(feature = "sftp")]
+    pub async fn new_sftp(address: &str, user: &str, auth: SftpAuth, root: &str) -> Result<Self> {
+        Ok(Location::Sftp(Sftp::new(address, user, auth, root).await?))
+    }
 
+    /// Create a new WebDAV location with the given base url.
+    /// The containers are stored as collections under the base url.
+    #[cfg(feature = "webdav")]
+    pub async fn new_webdav(url: &str, auth: WebDavAuth) -> Result<Self> {
+        Ok(Location::WebDav(WebDav::new(url, auth).await?))
+    }
+
+    /// Create a new read-only HTTP(S) location with the given base url.
+    /// Listing the containers and items is not supported by this location.
+    #[cfg(feature = "http")]
+    pub async fn new_http(url: &str) -> Result<Self> {
+        Ok(Location::Http(Http::new(url).await?))
+    }
+
+    /// Create a new FTP location on the server with the given address, e.g. `example.com:21`.
+    /// The containers are stored as directories under the root path.
+    #[cfg(feature = "ftp")]
+    pub async fn new_ftp(address: &str, user: &str, password: &str, root: &str) -> Result<Self> {
+        Ok(Location::Ftp(
+            Ftp::new(address, user, password, root, false).await?,
+        ))
+    }
+
+    /// Create a new FTPS location, which is secured with explicit TLS
+    #[cfg(feature = "ftp")]
+    pub async fn new_ftps(address: &str, user: &str, password: &str, root: &str) -> Result<Self> {
+        Ok(Location::Ftp(
+            Ftp::new(address, user, password, root, true).await?,
+        ))
+    }
+
+    /// Create a new OpenStack Swift location, authenticated with Keystone v3
+    #[cfg(feature = "swift")]
+    pub async fn new_swift(auth: SwiftAuth) -> Result<Self> {
+        Ok(Location::Swift(Swift::new(auth).await?))
+    }
+
+    /// Create a new Backblaze B2 location with the given application key
+    #[cfg(feature = "b2")]
+    pub async fn new_b2(key_id: &str, application_key: &str) -> Result<Self> {
+        Ok(Location::B2(B2::new(key_id, application_key).await?))
+    }
+
+    /// Create a new location in a `.tar`, `.tar.gz` or `.zip` file
+    #[cfg(feature = "archive")]
+    pub async fn new_archive(path: &str, mode: ArchiveMode) -> Result<Self> {
+        Ok(Location::Archive(Archive::new(path, mode).await?))
+    }
+
+    /// Create a new location in a SQLite database file, which gets created if not available
+    #[cfg(feature = "sqlite")]
+    pub async fn new_sqlite(path: &str) -> Result<Self> {
+        Ok(Location::Sqlite(Sqlite::new(path).await?))
+    }
+
+    /// Wrap the location, to store the items deduplicated in chunks on it
+    #[cfg(feature = "dedup")]
+    pub async fn new_dedup(inner: Location) -> Result<Self> {
+        Ok(Location::Dedup(Dedup::new(inner).await?))
+    }
+
+    /// Wrap the location, to retry failed operations with the default policy
+    #[cfg(feature = "retry")]
+    pub async fn new_retry(inner: Location) -> Result<Self> {
+        Ok(Location::Retry(Retry::new(inner)))
+    }
+
+    /// Wrap the location, to keep copies of read items in the cache location
+    #[cfg(feature = "cache")]
+    pub async fn new_cache(inner: Location, cache: Location) -> Result<Self> {
+        Ok(Location::Cache(Cache::new(inner, cache).await?))
+    }
+
+    /// Wrap the location, to record metrics of all operations
+    #[cfg(feature = "metrics")]
+    pub async fn new_metered(inner: Location) -> Result<Self> {
+        Ok(Location::Metered(Metered::new(inner)))
+    }
+
+    /// Wrap the location, to encrypt its items with the keys
+    #[cfg(feature = "encryption")]
+    pub async fn new_encrypted(inner: Location, keys: Keyring) -> Result<Self> {
+        Ok(Location::Encrypted(Encrypted::new(inner, keys)))
+    }
+
+    /// Wrap the location, to compress its items with the codec
+    #[cfg(feature = "compression")]
+    pub async fn new_compressed(inner: Location, codec: Codec) -> Result<Self> {
+        Ok(Location::Compressed(Compressed::new(inner, codec)))
+    }
+
+    /// Wrap the location, to store the items under the prefix in its container
+    #[cfg(feature = "prefix")]
+    pub async fn new_prefixed(inner: Location, container: &str, prefix: &str) -> Result<Self> {
+        Ok(Location::Prefixed(Prefixed::new(inner, container, prefix)?))
+    }
+
+    /// Wrap the location, to only allow the operations of the policy
+    #[cfg(feature = "guard")]
+    pub async fn new_guarded(inner: Location, policy: GuardPolicy) -> Result<Self> {
+        Ok(Location::Guarded(Guarded::new(inner, policy)))
+    }
+
+    /// Wrap the location, to limit its operations and bytes per second, which must be positive
+    #[cfg(feature = "throttle")]
+    pub async fn new_throttled(
+        inner: Location,
+        operations_per_second: f64,
+        bytes_per_second: u64,
+    ) -> Result<Self> {
+        Ok(Location::Throttled(
+            Throttled::new(inner)
+                .with_operations_per_second(operations_per_second)?
+                .with_bytes_per_second(bytes_per_second)?,
+        ))
+    }
+
+    /// Name of the backend of the location, e.g. for logs
+    pub fn backend(&self) -> &'static str {
         match self {
-            Location::Local(l) => l.create_container(&container).await,
-            Location::Gcs(l) => l.create_container(&container).await,
-            Location::S3(l) => l.create_container(&container).await,
+            Location::Local(_) => "local",
+            Location::Memory(_) => "memory",
+            #[cfg(feature = "gcs")]
+            Location::Gcs(_) => "gcs",
+            #[cfg(feature = "s3")]
+            Location::S3(_) => "s3",
+            #[cfg(feature = "azure")]
+            Location::Azure(_) => "azure",
+            #[cfg(feature = "sftp")]
+            Location::Sftp(_) => "sftp",
+            #[cfg(feature = "webdav")]
+            Location::WebDav(_) => "webdav",
+            #[cfg(feature = "http")]
+            Location::Http(_) => "http",
+            #[cfg(feature = "ftp")]
+            Location::Ftp(_) => "ftp",
+            #[cfg(feature = "swift")]
+            Location::Swift(_) => "swift",
+            #[cfg(feature = "b2")]
+            Location::B2(_) => "b2",
+            #[cfg(feature = "archive")]
+            Location::Archive(_) => "archive",
+            #[cfg(feature = "sqlite")]
+            Location::Sqlite(_) => "sqlite",
+            #[cfg(feature = "dedup")]
+            Location::Dedup(_) => "dedup",
+            #[cfg(feature = "retry")]
+            Location::Retry(_) => "retry",
+            #[cfg(feature = "cache")]
+            Location::Cache(_) => "cache",
+            #[cfg(feature = "metrics")]
+            Location::Metered(_) => "metered",
+            #[cfg(feature = "encryption")]
+            Location::Encrypted(_) => "encrypted",
+            #[cfg(feature = "compression")]
+            Location::Compressed(_) => "compressed",
+            #[cfg(feature = "prefix")]
+            Location::Prefixed(_) => "prefixed",
+            #[cfg(feature = "guard")]
+            Location::Guarded(_) => "guarded",
+            #[cfg(feature = "throttle")]
+            Location::Throttled(_) => "throttled",
         }
     }
 
-    pub async fn remove_container(&mut self, container: &str) -> Result<()> {
-        match self {
-            Location::Local(l) => l.remove_container(container).await,
-            Location::Gcs(l) => l.remove_container(container).await,
-            Location::S3(l) => l.remove_container(container).await,
+    /// Name of the backend, which stores the data of the location.
+    /// Wrappers are skipped, e.g. it is `gcs` for a cache in front of GCS
+    pub fn storage_backend(&self) -> &'static str {
+        match self.wrapped() {
+            Some(inner) => inner.storage_backend(),
+            None => self.backend(),
         }
     }
 
-    pub async fn items(&mut self, container: &str) -> Result<Vec<String>> {
+    /// The wrapped location, if the location is a wrapper
+    fn wrapped(&self) -> Option<&Location> {
         match self {
-            Location::Local(l) => l.items(container).await,
-            Location::Gcs(l) => l.items(container).await,
-            Location::S3(l) => l.items(container).await,
+            #[cfg(feature = "dedup")]
+            Location::Dedup(l) => Some(&l.inner),
+            #[cfg(feature = "retry")]
+            Location::Retry(l) => Some(&l.inner),
+            #[cfg(feature = "cache")]
+            Location::Cache(l) => Some(&l.inner),
+            #[cfg(feature = "metrics")]
+            Location::Metered(l) => Some(&l.inner),
+            #[cfg(feature = "encryption")]
+            Location::Encrypted(l) => Some(&l.inner),
+            #[cfg(feature = "compression")]
+            Location::Compressed(l) => Some(&l.inner),
+            #[cfg(feature = "prefix")]
+            Location::Prefixed(l) => Some(&l.inner),
+            #[cfg(feature = "guard")]
+            Location::Guarded(l) => Some(&l.inner),
+            #[cfg(feature = "throttle")]
+            Location::Throttled(l) => Some(&l.inner),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    /// Run the operation within a span, which records the location and the outcome
+    async fn traced<T>(
+        &self,
+        operation: &'static str,
+        container: Option<&str>,
+        item: Option<&str>,
+        future: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        use tracing::Instrument;
+
+        let span = tracing::info_span!(
+            "stow",
+            backend = self.backend(),
+            storage_backend = self.storage_backend(),
+            operation,
+            container,
+            item,
+            bytes = tracing::field::Empty,
+            outcome = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+
+        let res = future.instrument(span.clone()).await;
+        match &res {
+            Ok(_) => {
+                span.record("outcome", "ok");
+            }
+            Err(e) => {
+                span.record("outcome", "error");
+                span.record("error", tracing::field::display(e));
+            }
         }
+        res
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    /// Run the operation, spans are only created with the tracing feature
+    async fn traced<T>(
+        &self,
+        _operation: &'static str,
+        _container: Option<&str>,
+        _item: Option<&str>,
+        future: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        future.await
+    }
+
+    pub async fn containers(&self) -> Result<Vec<String>> {
+        let operation = async {
+            match self {
+                Location::Local(l) => l.containers().await,
+                Location::Memory(l) => l.containers().await,
+                #[cfg(feature = "gcs")]
+                Location::Gcs(l) => l.containers().await,
+                #[cfg(feature = "s3")]
+                Location::S3(l) => l.containers().await,
+                #[cfg(feature = "azure")]
+                Location::Azure(l) => l.containers().await,
+                #[cfg(feature = "sftp")]
+                Location::Sftp(l) => l.containers().await,
+                #[cfg(feature = "webdav")]
+                Location::WebDav(l) => l.containers().await,
+                #[cfg(feature = "http")]
+                Location::Http(l) => l.containers().await,
+                #[cfg(feature = "ftp")]
+                Location::Ftp(l) => l.containers().await,
+                #[cfg(feature = "swift")]
+                Location::Swift(l) => l.containers().await,
+                #[cfg(feature = "b2")]
+                Location::B2(l) => l.containers().await,
+                #[cfg(feature = "archive")]
+                Location::Archive(l) => l.containers().await,
+                #[cfg(feature = "sqlite")]
+                Location::Sqlite(l) => l.containers().await,
+                #[cfg(feature = "dedup")]
+                Location::Dedup(l) => l.containers().await,
+                #[cfg(feature = "retry")]
+                Location::Retry(l) => l.containers().await,
+                #[cfg(feature = "cache")]
+                Location::Cache(l) => l.containers().await,
+                #[cfg(feature = "metrics")]
+                Location::Metered(l) => l.containers().await,
+                #[cfg(feature = "encryption")]
+                Location::Encrypted(l) => l.containers().await,
+                #[cfg(feature = "compression")]
+                Location::Compressed(l) => l.containers().await,
+                #[cfg(feature = "prefix")]
+                Location::Prefixed(l) => l.containers().await,
+                #[cfg(feature = "guard")]
+                Location::Guarded(l) => l.containers().await,
+                #[cfg(feature = "throttle")]
+                Location::Throttled(l) => l.containers().await,
+            }
+        };
+        self.traced("containers", None, None, operation).await
+    }
+
+    pub async fn create_container(&self, container: &str) -> Result<()> {
+        let container = util::streamline(&container);
+
+        let operation = async {
+            match self {
+                Location::Local(l) => l.create_container(&container).await,
+                Location::Memory(l) => l.create_container(&container).await,
+                #[cfg(feature = "gcs")]
+                Location::Gcs(l) => l.create_container(&container).await,
+                #[cfg(feature = "s3")]
+                Location::S3(l) => l.create_container(&container).await,
+                #[cfg(feature = "azure")]
+                Location::Azure(l) => l.create_container(&container).await,
+                #[cfg(feature = "sftp")]
+                Location::Sftp(l) => l.create_container(&container).await,
+                #[cfg(feature = "webdav")]
+                Location::WebDav(l) => l.create_container(&container).await,
+                #[cfg(feature = "http")]
+                Location::Http(l) => l.create_container(&container).await,
+                #[cfg(feature = "ftp")]
+                Location::Ftp(l) => l.create_container(&container).await,
+                #[cfg(feature = "swift")]
+                Location::Swift(l) => l.create_container(&container).await,
+                #[cfg(feature = "b2")]
+                Location::B2(l) => l.create_container(&container).await,
+                #[cfg(feature = "archive")]
+                Location::Archive(l) => l.create_container(&container).await,
+                #[cfg(feature = "sqlite")]
+                Location::Sqlite(l) => l.create_container(&container).await,
+                #[cfg(feature = "dedup")]
+                Location::Dedup(l) => l.create_container(&container).await,
+                #[cfg(feature = "retry")]
+                Location::Retry(l) => l.create_container(&container).await,
+                #[cfg(feature = "cache")]
+                Location::Cache(l) => l.create_container(&container).await,
+                #[cfg(feature = "metrics")]
+                Location::Metered(l) => l.create_container(&container).await,
+                #[cfg(feature = "encryption")]
+                Location::Encrypted(l) => l.create_container(&container).await,
+                #[cfg(feature = "compression")]
+                Location::Compressed(l) => l.create_container(&container).await,
+                #[cfg(feature = "prefix")]
+                Location::Prefixed(l) => l.create_container(&container).await,
+                #[cfg(feature = "guard")]
+                Location::Guarded(l) => l.create_container(&container).await,
+                #[cfg(feature = "throttle")]
+                Location::Throttled(l) => l.create_container(&container).await,
+            }
+        };
+        self.traced("create_container", Some(&container), None, operation)
+            .await
+    }
+
+    pub async fn remove_container(&self, container: &str) -> Result<()> {
+        let operation = async {
+            match self {
+                Location::Local(l) => l.remove_container(container).await,
+                Location::Memory(l) => l.remove_container(container).await,
+                #[cfg(feature = "gcs")]
+                Location::Gcs(l) => l.remove_container(container).await,
+                #[cfg(feature = "s3")]
+                Location::S3(l) => l.remove_container(container).await,
+                #[cfg(feature = "azure")]
+                Location::Azure(l) => l.remove_container(container).await,
+                #[cfg(feature = "sftp")]
+                Location::Sftp(l) => l.remove_container(container).await,
+                #[cfg(feature = "webdav")]
+                Location::WebDav(l) => l.remove_container(container).await,
+                #[cfg(feature = "http")]
+                Location::Http(l) => l.remove_container(container).await,
+                #[cfg(feature = "ftp")]
+                Location::Ftp(l) => l.remove_container(container).await,
+                #[cfg(feature = "swift")]
+                Location::Swift(l) => l.remove_container(container).await,
+                #[cfg(feature = "b2")]
+                Location::B2(l) => l.remove_container(container).await,
+                #[cfg(feature = "archive")]
+                Location::Archive(l) => l.remove_container(container).await,
+                #[cfg(feature = "sqlite")]
+                Location::Sqlite(l) => l.remove_container(container).await,
+                #[cfg(feature = "dedup")]
+                Location::Dedup(l) => l.remove_container(container).await,
+                #[cfg(feature = "retry")]
+                Location::Retry(l) => l.remove_container(container).await,
+                #[cfg(feature = "cache")]
+                Location::Cache(l) => l.remove_container(container).await,
+                #[cfg(feature = "metrics")]
+                Location::Metered(l) => l.remove_container(container).await,
+                #[cfg(feature = "encryption")]
+                Location::Encrypted(l) => l.remove_container(container).await,
+                #[cfg(feature = "compression")]
+                Location::Compressed(l) => l.remove_container(container).await,
+                #[cfg(feature = "prefix")]
+                Location::Prefixed(l) => l.remove_container(container).await,
+                #[cfg(feature = "guard")]
+                Location::Guarded(l) => l.remove_container(container).await,
+                #[cfg(feature = "throttle")]
+                Location::Throttled(l) => l.remove_container(container).await,
+            }
+        };
+        self.traced("remove_container", Some(container), None, operation)
+            .await
+    }
+
+    pub async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let operation = async {
+            match self {
+                Location::Local(l) => l.items(container).await,
+                Location::Memory(l) => l.items(container).await,
+                #[cfg(feature = "gcs")]
+                Location::Gcs(l) => l.items(container).await,
+                #[cfg(feature = "s3")]
+                Location::S3(l) => l.items(container).await,
+                #[cfg(feature = "azure")]
+                Location::Azure(l) => l.items(container).await,
+                #[cfg(feature = "sftp")]
+                Location::Sftp(l) => l.items(container).await,
+                #[cfg(feature = "webdav")]
+                Location::WebDav(l) => l.items(container).await,
+                #[cfg(feature = "http")]
+                Location::Http(l) => l.items(container).await,
+                #[cfg(feature = "ftp")]
+                Location::Ftp(l) => l.items(container).await,
+                #[cfg(feature = "swift")]
+                Location::Swift(l) => l.items(container).await,
+                #[cfg(feature = "b2")]
+                Location::B2(l) => l.items(container).await,
+                #[cfg(feature = "archive")]
+                Location::Archive(l) => l.items(container).await,
+                #[cfg(feature = "sqlite")]
+                Location::Sqlite(l) => l.items(container).await,
+                #[cfg(feature = "dedup")]
+                Location::Dedup(l) => l.items(container).await,
+                #[cfg(feature = "retry")]
+                Location::Retry(l) => l.items(container).await,
+                #[cfg(feature = "cache")]
+                Location::Cache(l) => l.items(container).await,
+                #[cfg(feature = "metrics")]
+                Location::Metered(l) => l.items(container).await,
+                #[cfg(feature = "encryption")]
+                Location::Encrypted(l) => l.items(container).await,
+                #[cfg(feature = "compression")]
+                Location::Compressed(l) => l.items(container).await,
+                #[cfg(feature = "prefix")]
+                Location::Prefixed(l) => l.items(container).await,
+                #[cfg(feature = "guard")]
+                Location::Guarded(l) => l.items(container).await,
+                #[cfg(feature = "throttle")]
+                Location::Throttled(l) => l.items(container).await,
+            }
+        };
+        self.traced("items", Some(container), None, operation).await
     }
 
     pub async fn create_item(
-        &mut self,
+        &self,
         container: &str,
         item: &str,
         reader: (impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static),
     ) -> Result<()> {
         let container = util::streamline(container);
 
-        match self {
-            Location::Local(l) => l.create_item(&container, item, reader).await,
-            Location::Gcs(l) => l.create_item(&container, item, reader).await,
-            Location::S3(l) => l.create_item(&container, item, reader).await,
-        }
+        // boxed, so wrapped locations don't nest the reader types endlessly
+        #[cfg(feature = "tracing")]
+        let (reader, bytes) = util::CountingReader::new(reader);
+        #[cfg(feature = "tracing")]
+        let reader: Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync> = Box::new(reader);
+
+        let operation = async {
+            let res = match self {
+                Location::Local(l) => l.create_item(&container, item, reader).await,
+                Location::Memory(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "gcs")]
+                Location::Gcs(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "s3")]
+                Location::S3(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "azure")]
+                Location::Azure(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "sftp")]
+                Location::Sftp(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "webdav")]
+                Location::WebDav(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "http")]
+                Location::Http(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "ftp")]
+                Location::Ftp(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "swift")]
+                Location::Swift(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "b2")]
+                Location::B2(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "archive")]
+                Location::Archive(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "sqlite")]
+                Location::Sqlite(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "dedup")]
+                Location::Dedup(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "retry")]
+                Location::Retry(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "cache")]
+                Location::Cache(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "metrics")]
+                Location::Metered(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "encryption")]
+                Location::Encrypted(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "compression")]
+                Location::Compressed(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "prefix")]
+                Location::Prefixed(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "guard")]
+                Location::Guarded(l) => l.create_item(&container, item, reader).await,
+                #[cfg(feature = "throttle")]
+                Location::Throttled(l) => l.create_item(&container, item, reader).await,
+            };
+
+            #[cfg(feature = "tracing")]
+            tracing::Span::current()
+                .record("bytes", bytes.load(std::sync::atomic::Ordering::SeqCst));
+            res
+        };
+        self.traced("create_item", Some(&container), Some(item), operation)
+            .await
     }
 
     pub async fn read_item(
-        &mut self,
+        &self,
         container: &str,
         item: &str,
     ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
-        match self {
-            Location::Local(l) => l.read_item(container, item).await,
-            Location::Gcs(l) => l.read_item(container, item).await,
-            Location::S3(l) => l.read_item(container, item).await,
-        }
+        let operation = async {
+            let reader = match self {
+                Location::Local(l) => l.read_item(container, item).await,
+                Location::Memory(l) => l.read_item(container, item).await,
+                #[cfg(feature = "gcs")]
+                Location::Gcs(l) => l.read_item(container, item).await,
+                #[cfg(feature = "s3")]
+                Location::S3(l) => l.read_item(container, item).await,
+                #[cfg(feature = "azure")]
+                Location::Azure(l) => l.read_item(container, item).await,
+                #[cfg(feature = "sftp")]
+                Location::Sftp(l) => l.read_item(container, item).await,
+                #[cfg(feature = "webdav")]
+                Location::WebDav(l) => l.read_item(container, item).await,
+                #[cfg(feature = "http")]
+                Location::Http(l) => l.read_item(container, item).await,
+                #[cfg(feature = "ftp")]
+                Location::Ftp(l) => l.read_item(container, item).await,
+                #[cfg(feature = "swift")]
+                Location::Swift(l) => l.read_item(container, item).await,
+                #[cfg(feature = "b2")]
+                Location::B2(l) => l.read_item(container, item).await,
+                #[cfg(feature = "archive")]
+                Location::Archive(l) => l.read_item(container, item).await,
+                #[cfg(feature = "sqlite")]
+                Location::Sqlite(l) => l.read_item(container, item).await,
+                #[cfg(feature = "dedup")]
+                Location::Dedup(l) => l.read_item(container, item).await,
+                #[cfg(feature = "retry")]
+                Location::Retry(l) => l.read_item(container, item).await,
+                #[cfg(feature = "cache")]
+                Location::Cache(l) => l.read_item(container, item).await,
+                #[cfg(feature = "metrics")]
+                Location::Metered(l) => l.read_item(container, item).await,
+                #[cfg(feature = "encryption")]
+                Location::Encrypted(l) => l.read_item(container, item).await,
+                #[cfg(feature = "compression")]
+                Location::Compressed(l) => l.read_item(container, item).await,
+                #[cfg(feature = "prefix")]
+                Location::Prefixed(l) => l.read_item(container, item).await,
+                #[cfg(feature = "guard")]
+                Location::Guarded(l) => l.read_item(container, item).await,
+                #[cfg(feature = "throttle")]
+                Location::Throttled(l) => l.read_item(container, item).await,
+            }?;
+
+            // the bytes are recorded, when the reader is dropped
+            #[cfg(feature = "tracing")]
+            let reader: Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync> =
+                Box::new(util::TracedReader::new(reader));
+            Ok(reader)
+        };
+        self.traced("read_item", Some(container), Some(item), operation)
+            .await
     }
 
-    pub async fn remove_item(&mut self, container: &str, item: &str) -> Result<()> {
-        match self {
-            Location::Local(l) => l.remove_item(container, item).await,
-            Location::Gcs(l) => l.remove_item(container, item).await,
-            Location::S3(l) => l.remove_item(container, item).await,
-        }
+    pub async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        let operation = async {
+            match self {
+                Location::Local(l) => l.remove_item(container, item).await,
+                Location::Memory(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "gcs")]
+                Location::Gcs(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "s3")]
+                Location::S3(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "azure")]
+                Location::Azure(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "sftp")]
+                Location::Sftp(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "webdav")]
+                Location::WebDav(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "http")]
+                Location::Http(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "ftp")]
+                Location::Ftp(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "swift")]
+                Location::Swift(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "b2")]
+                Location::B2(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "archive")]
+                Location::Archive(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "sqlite")]
+                Location::Sqlite(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "dedup")]
+                Location::Dedup(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "retry")]
+                Location::Retry(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "cache")]
+                Location::Cache(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "metrics")]
+                Location::Metered(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "encryption")]
+                Location::Encrypted(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "compression")]
+                Location::Compressed(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "prefix")]
+                Location::Prefixed(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "guard")]
+                Location::Guarded(l) => l.remove_item(container, item).await,
+                #[cfg(feature = "throttle")]
+                Location::Throttled(l) => l.remove_item(container, item).await,
+            }
+        };
+        self.traced("remove_item", Some(container), Some(item), operation)
+            .await
     }
 }
 
 mod util {
     use super::*;
 
+    #[cfg(feature = "azure")]
+    /// Collect the text of all elements, which are nested by the given path of element names.
+    /// The path doesn't need to start at the root element.
+    pub fn xml_values(xml: &str, path: &[&str]) -> Result<Vec<String>> {
+        use quick_xml::events::Event;
+
+        let mut reader = quick_xml::Reader::from_str(xml);
+        reader.trim_text(true);
+
+        let mut buf = vec![];
+        let mut stack: Vec<String> = vec![];
+        let mut values = vec![];
+
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(e) => {
+                    stack.push(String::from_utf8_lossy(e.local_name()).to_string());
+                }
+                Event::End(_) => {
+                    stack.pop();
+                }
+                Event::Text(t)
+                    if stack.ends_with(&path.iter().map(|p| p.to_string()).collect::<Vec<_>>()) =>
+                {
+                    values.push(t.unescape_and_decode(&reader)?);
+                }
+                Event::Eof => return Ok(values),
+                _ => {}
+            }
+            buf.clear();
+        }
+    }
+
+    #[cfg(any(feature = "archive", feature = "sftp", feature = "sqlite"))]
+    /// Size of the chunks which are passed to and from blocking io
+    const BLOCKING_CHUNK_SIZE: usize = 64 * 1024;
+
+    #[cfg(feature = "dedup")]
+    /// Convert the error into an io error, to return it from an item reader
+    pub fn io_error(e: StowError) -> std::io::Error {
+        match e {
+            StowError::Disconnect(e) => e,
+            e => std::io::Error::other(e.to_string()),
+        }
+    }
+
+    #[cfg(any(feature = "archive", feature = "sftp", feature = "sqlite"))]
+    /// Writer which sends everything written to it into a channel
+    struct ChannelWriter(futures::channel::mpsc::Sender<std::io::Result<bytes::Bytes>>);
+
+    #[cfg(any(feature = "archive", feature = "sftp", feature = "sqlite"))]
+    impl std::io::Write for ChannelWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            use futures::SinkExt;
+
+            // fails when the reader got dropped
+            futures::executor::block_on(self.0.send(Ok(bytes::Bytes::copy_from_slice(buf))))
+                .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(any(feature = "archive", feature = "sftp", feature = "sqlite"))]
+    /// Run a function on the blocking thread pool and stream everything it writes as item reader
+    pub fn blocking_stream(
+        write: impl FnOnce(&mut dyn std::io::Write) -> std::io::Result<()> + Send + 'static,
+    ) -> Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync> {
+        use futures::SinkExt;
+        use std::io::Write;
+
+        let (send, recv) = futures::channel::mpsc::channel(4);
+
+        tokio::task::spawn_blocking(move || {
+            let mut writer =
+                std::io::BufWriter::with_capacity(BLOCKING_CHUNK_SIZE, ChannelWriter(send));
+            let res = write(&mut writer).and_then(|_| writer.flush());
+
+            // hand the error over to the reader
+            if let (Err(e), Ok(writer)) = (res, writer.into_inner()) {
+                let mut send = writer.0;
+                let _ = futures::executor::block_on(send.send(Err(e)));
+            }
+        });
+
+        Box::new(tokio_util::io::StreamReader::new(recv))
+    }
+
+    #[cfg(feature = "sftp")]
+    /// Read from a blocking reader on the blocking thread pool and stream the data as item reader
+    pub fn blocking_reader(
+        mut read: impl std::io::Read + Send + 'static,
+    ) -> Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync> {
+        blocking_stream(move |writer| std::io::copy(&mut read, writer).map(|_| ()))
+    }
+
+    #[cfg(feature = "sftp")]
+    /// Copy all data of the reader into a blocking writer, without blocking the runtime
+    pub async fn copy_to_blocking<W: std::io::Write + Send + 'static>(
+        reader: &mut (impl tokio::io::AsyncRead + Unpin),
+        mut writer: W,
+    ) -> Result<W> {
+        use tokio::io::AsyncReadExt;
+
+        let mut buf = vec![0; BLOCKING_CHUNK_SIZE];
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+
+            let chunk = buf[..n].to_vec();
+            writer = tokio::task::spawn_blocking(move || writer.write_all(&chunk).map(|_| writer))
+                .await??;
+        }
+
+        Ok(tokio::task::spawn_blocking(move || writer.flush().map(|_| writer)).await??)
+    }
+
+    #[cfg(any(feature = "cache", feature = "metrics", feature = "tracing"))]
+    /// Reader which counts the read bytes
+    pub struct CountingReader<R> {
+        reader: R,
+        count: std::sync::Arc<std::sync::atomic::AtomicU64>,
+    }
+
+    #[cfg(any(feature = "cache", feature = "metrics", feature = "tracing"))]
+    impl<R> CountingReader<R> {
+        /// Wrap the reader, the returned counter holds the number of bytes read so far
+        pub fn new(reader: R) -> (Self, std::sync::Arc<std::sync::atomic::AtomicU64>) {
+            let count = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+            (
+                Self {
+                    reader,
+                    count: count.clone(),
+                },
+                count,
+            )
+        }
+    }
+
+    #[cfg(any(feature = "cache", feature = "metrics", feature = "tracing"))]
+    impl<R: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for CountingReader<R> {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            let before = buf.filled().len();
+            let res = std::pin::Pin::new(&mut self.reader).poll_read(cx, buf);
+            self.count.fetch_add(
+                (buf.filled().len() - before) as u64,
+                std::sync::atomic::Ordering::SeqCst,
+            );
+            res
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    /// Item reader, which records the number of read bytes in the current span, when it is dropped
+    pub struct TracedReader {
+        reader: Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>,
+        span: tracing::Span,
+        bytes: u64,
+    }
+
+    #[cfg(feature = "tracing")]
+    impl TracedReader {
+        pub fn new(reader: Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>) -> Self {
+            Self {
+                reader,
+                span: tracing::Span::current(),
+                bytes: 0,
+            }
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tokio::io::AsyncRead for TracedReader {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            let before = buf.filled().len();
+            let res = std::pin::Pin::new(&mut self.reader).poll_read(cx, buf);
+            self.bytes += (buf.filled().len() - before) as u64;
+            res
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl Drop for TracedReader {
+        fn drop(&mut self) {
+            self.span.record("bytes", self.bytes);
+        }
+    }
+
+    #[cfg(feature = "reqwest")]
+    /// Turn an unsuccessful http response into an error
+    pub fn http_status(res: reqwest::Response) -> Result<reqwest::Response> {
+        if !res.status().is_success() {
+            return Err(StowError::HttpStatusError(res.status().as_u16()));
+        }
+        Ok(res)
+    }
+
+    #[cfg(feature = "reqwest")]
+    /// Stream the body of a http response as item reader
+    pub fn response_reader(
+        res: reqwest::Response,
+    ) -> Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync> {
+        use futures::TryStreamExt;
+
+        let stream = res.bytes_stream().map_err(std::io::Error::other);
+        Box::new(tokio_util::io::StreamReader::new(stream))
+    }
+
     pub fn streamline(input: &str) -> String {
         // reformat the name
-        let reg = regex::Regex::new("[^a-z0-9\\-]").unwrap();
-        let mut res = reg.replace_all(input.to_lowercase().trim(), "").to_string();
+        let mut res: String = input
+            .to_lowercase()
+            .trim()
+            .chars()
+            .filter(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '-')
+            .collect();
         if res.starts_with('-') {
             res.remove(0);
         }
@@ -144,11 +1088,7 @@ mod util {
         let (base, typ) = input.split_at(pos);
 
         // reformat the name
-        let reg = regex::Regex::new("[^a-z0-9\\-]").unwrap();
-        let mut res = reg.replace_all(base.to_lowercase().trim(), "").to_string();
-        if res.starts_with('-') {
-            res.remove(0);
-        }
+        let res = streamline(base);
 
         // add the file ending if it is a item
         let mut out = String::from(&res);
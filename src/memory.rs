@@ -0,0 +1,123 @@
+use crate::*;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+type Containers = BTreeMap<String, BTreeMap<String, Arc<Vec<u8>>>>;
+
+/// Location which keeps all containers and items in memory.
+/// Clones share the same storage.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLocation {
+    containers: Arc<RwLock<Containers>>,
+}
+
+impl MemoryLocation {
+    pub async fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    fn not_found(what: &str, name: &str) -> StowError {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} '{}' not found", what, name),
+        )
+        .into()
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for MemoryLocation {
+    async fn containers(&self) -> Result<Vec<String>> {
+        let containers = self.containers.read().map_err(|_| StowError::Unknown)?;
+        Ok(containers.keys().cloned().collect())
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        let mut containers = self.containers.write().map_err(|_| StowError::Unknown)?;
+        containers.entry(container.to_string()).or_default();
+        Ok(())
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        let container = util::streamline(container);
+
+        let mut containers = self.containers.write().map_err(|_| StowError::Unknown)?;
+        containers
+            .remove(&container)
+            .ok_or_else(|| Self::not_found("container", &container))?;
+        Ok(())
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let container = util::streamline(container);
+
+        let containers = self.containers.read().map_err(|_| StowError::Unknown)?;
+        let items = containers
+            .get(&container)
+            .ok_or_else(|| Self::not_found("container", &container))?;
+        Ok(items.keys().cloned().collect())
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let item = util::streamline_item(item)?;
+
+        // read the data before locking, to not block other tasks
+        let mut data = vec![];
+        reader.read_to_end(&mut data).await?;
+
+        let mut containers = self.containers.write().map_err(|_| StowError::Unknown)?;
+        let items = containers
+            .get_mut(container)
+            .ok_or_else(|| Self::not_found("container", container))?;
+        items.insert(item, Arc::new(data));
+        Ok(())
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let containers = self.containers.read().map_err(|_| StowError::Unknown)?;
+        let data = containers
+            .get(&container)
+            .ok_or_else(|| Self::not_found("container", &container))?
+            .get(&item)
+            .ok_or_else(|| Self::not_found("item", &item))?
+            .clone();
+
+        Ok(Box::new(std::io::Cursor::new(SharedData(data))))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let mut containers = self.containers.write().map_err(|_| StowError::Unknown)?;
+        containers
+            .get_mut(&container)
+            .ok_or_else(|| Self::not_found("container", &container))?
+            .remove(&item)
+            .ok_or_else(|| Self::not_found("item", &item))?;
+        Ok(())
+    }
+}
+
+/// Item data which is shared with the store, so reading doesn't copy it.
+struct SharedData(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for SharedData {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
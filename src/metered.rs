@@ -0,0 +1,172 @@
+use crate::*;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Number of operations by backend, operation and outcome
+pub const METRIC_OPERATIONS: &str = "stow_operations_total";
+/// Number of failed operations by backend, operation and kind of the error
+pub const METRIC_ERRORS: &str = "stow_errors_total";
+/// Histogram of the duration of the operations by backend and operation
+pub const METRIC_DURATION: &str = "stow_operation_duration_seconds";
+/// Number of bytes of created items by backend
+pub const METRIC_BYTES_WRITTEN: &str = "stow_bytes_written_total";
+/// Number of bytes of read items by backend
+pub const METRIC_BYTES_READ: &str = "stow_bytes_read_total";
+
+/// Location wrapper, which records metrics of all operations through the `metrics` facade.
+/// They are exported by the recorder installed by the application, e.g. a Prometheus exporter.
+///
+/// The duration of reading items only covers opening them, the read bytes are counted while reading.
+#[derive(Clone)]
+pub struct Metered {
+    pub(crate) inner: Arc<Location>,
+    backend: &'static str,
+}
+
+impl Metered {
+    /// Wrap the location, the backend storing its data is used as label of the metrics,
+    /// so wrapped locations are labeled e.g. `gcs` instead of `retry`
+    pub fn new(inner: Location) -> Self {
+        metrics::describe_counter!(METRIC_OPERATIONS, "Number of stow operations");
+        metrics::describe_counter!(METRIC_ERRORS, "Number of failed stow operations");
+        metrics::describe_histogram!(
+            METRIC_DURATION,
+            metrics::Unit::Seconds,
+            "Duration of stow operations"
+        );
+        metrics::describe_counter!(
+            METRIC_BYTES_WRITTEN,
+            metrics::Unit::Bytes,
+            "Bytes of items created by stow"
+        );
+        metrics::describe_counter!(
+            METRIC_BYTES_READ,
+            metrics::Unit::Bytes,
+            "Bytes of items read by stow"
+        );
+
+        Self {
+            backend: inner.storage_backend(),
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Run the operation and record its duration and outcome
+    async fn measured<T>(
+        &self,
+        operation: &'static str,
+        future: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let start = Instant::now();
+        let res = future.await;
+
+        metrics::histogram!(
+            METRIC_DURATION,
+            "backend" => self.backend,
+            "operation" => operation
+        )
+        .record(start.elapsed().as_secs_f64());
+
+        let outcome = if res.is_ok() { "ok" } else { "error" };
+        metrics::counter!(
+            METRIC_OPERATIONS,
+            "backend" => self.backend,
+            "operation" => operation,
+            "outcome" => outcome
+        )
+        .increment(1);
+
+        if let Err(e) = &res {
+            metrics::counter!(
+                METRIC_ERRORS,
+                "backend" => self.backend,
+                "operation" => operation,
+                "kind" => e.kind()
+            )
+            .increment(1);
+        }
+        res
+    }
+}
+
+/// Item reader, which counts the read bytes
+struct MeteredReader {
+    reader: Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>,
+    bytes: metrics::Counter,
+}
+
+impl tokio::io::AsyncRead for MeteredReader {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let res = std::pin::Pin::new(&mut self.reader).poll_read(cx, buf);
+        self.bytes.increment((buf.filled().len() - before) as u64);
+        res
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Metered {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.measured("containers", self.inner.containers()).await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        self.measured("create_container", self.inner.create_container(container))
+            .await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        self.measured("remove_container", self.inner.remove_container(container))
+            .await
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        self.measured("items", self.inner.items(container)).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        reader: impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
+    ) -> Result<()> {
+        // boxed, so wrapped locations don't nest the reader types endlessly
+        let (reader, bytes) = util::CountingReader::new(reader);
+        let reader: Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync> = Box::new(reader);
+
+        let res = self
+            .measured(
+                "create_item",
+                self.inner.create_item(container, item, reader),
+            )
+            .await;
+
+        metrics::counter!(METRIC_BYTES_WRITTEN, "backend" => self.backend)
+            .increment(bytes.load(std::sync::atomic::Ordering::SeqCst));
+        res
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let reader = self
+            .measured("read_item", self.inner.read_item(container, item))
+            .await?;
+
+        Ok(Box::new(MeteredReader {
+            reader,
+            bytes: metrics::counter!(METRIC_BYTES_READ, "backend" => self.backend),
+        }))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        self.measured("remove_item", self.inner.remove_item(container, item))
+            .await
+    }
+}
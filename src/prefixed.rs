@@ -0,0 +1,125 @@
+use crate::*;
+use std::sync::Arc;
+
+/// Location wrapper, which is a view on the items under a prefix in one container of the wrapped location,
+/// e.g. to give every tenant its own location in a shared bucket.
+///
+/// The items are stored as `prefix.container.item`, because the backends only keep letters,
+/// digits and dashes in front of the first dot of item names. Containers of the view exist
+/// as long as they have items.
+#[derive(Clone)]
+pub struct Prefixed {
+    pub(crate) inner: Arc<Location>,
+    container: String,
+    prefix: String,
+}
+
+impl Prefixed {
+    /// Wrap the location, to store the items under the prefix in its container
+    pub fn new(inner: Location, container: &str, prefix: &str) -> Result<Self> {
+        Ok(Self {
+            inner: Arc::new(inner),
+            container: name(container)?,
+            prefix: name(prefix)?,
+        })
+    }
+
+    /// Name of the item in the wrapped location
+    fn item(&self, container: &str, item: &str) -> Result<String> {
+        let item = util::streamline_item(item)?;
+
+        // the file ending is kept as it is by the backends, so it must not leave the container
+        if item.contains('/') || item.contains('\\') || item.contains("..") {
+            return Err(StowError::InvalidNameError(item));
+        }
+
+        Ok(format!("{}.{}.{}", self.prefix, name(container)?, item))
+    }
+
+    /// Names of all items under the prefix, without the prefix
+    async fn all_items(&self) -> Result<Vec<String>> {
+        let prefix = format!("{}.", self.prefix);
+
+        Ok(self
+            .inner
+            .items(&self.container)
+            .await?
+            .into_iter()
+            .filter_map(|i| i.strip_prefix(&prefix).map(|i| i.to_string()))
+            .collect())
+    }
+}
+
+/// Streamline the name of a prefix or container, which must not be empty
+fn name(input: &str) -> Result<String> {
+    let name = util::streamline(input);
+    if name.is_empty() {
+        return Err(StowError::InvalidNameError(input.to_string()));
+    }
+    Ok(name)
+}
+
+#[async_trait::async_trait]
+impl Adapter for Prefixed {
+    async fn containers(&self) -> Result<Vec<String>> {
+        let mut containers: Vec<String> = self
+            .all_items()
+            .await?
+            .into_iter()
+            .filter_map(|i| i.split_once('.').map(|(c, _)| c.to_string()))
+            .collect();
+
+        containers.sort();
+        containers.dedup();
+        Ok(containers)
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        name(container)?;
+        self.inner.create_container(&self.container).await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        for item in self.items(container).await? {
+            self.inner
+                .remove_item(&self.container, &self.item(container, &item)?)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}.", name(container)?);
+
+        Ok(self
+            .all_items()
+            .await?
+            .into_iter()
+            .filter_map(|i| i.strip_prefix(&prefix).map(|i| i.to_string()))
+            .collect())
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        reader: impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
+    ) -> Result<()> {
+        let item = self.item(container, item)?;
+        self.inner.create_item(&self.container, &item, reader).await
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let item = self.item(container, item)?;
+        self.inner.read_item(&self.container, &item).await
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        let item = self.item(container, item)?;
+        self.inner.remove_item(&self.container, &item).await
+    }
+}
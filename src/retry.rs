@@ -0,0 +1,246 @@
+use crate::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How failed operations are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Backoff before the first retry
+    pub initial_backoff: Duration,
+    /// Upper limit of the backoff
+    pub max_backoff: Duration,
+    /// Factor the backoff grows by after every retry
+    pub multiplier: f64,
+    /// Wait a random time between zero and the backoff,
+    /// so many clients don't retry at the same time
+    pub jitter: bool,
+    /// Time budget of an operation, no retry is started which would wait beyond it
+    pub deadline: Option<Duration>,
+    /// Decides which errors are retried
+    pub retryable: fn(&StowError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+            deadline: None,
+            retryable: is_retryable,
+        }
+    }
+}
+
+/// Check if the error is likely transient, e.g. a dropped connection or a throttling response
+pub fn is_retryable(error: &StowError) -> bool {
+    use std::io::ErrorKind;
+
+    match error {
+        StowError::Disconnect(e) => matches!(
+            e.kind(),
+            ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::NotConnected
+                | ErrorKind::BrokenPipe
+                | ErrorKind::TimedOut
+                | ErrorKind::Interrupted
+                | ErrorKind::UnexpectedEof
+        ),
+        StowError::HttpStatusError(status) => retryable_status(*status),
+        #[cfg(feature = "reqwest")]
+        StowError::HttpError(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || matches!(e.status(), Some(s) if retryable_status(s.as_u16()))
+        }
+        // 4xx replies are transient negative completions
+        #[cfg(feature = "ftp")]
+        StowError::FtpError(code, _) => (400..500).contains(code),
+        #[cfg(feature = "sqlite")]
+        StowError::SqliteError(rusqlite::Error::SqliteFailure(e, _)) => matches!(
+            e.code,
+            rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+        ),
+        #[cfg(feature = "s3")]
+        StowError::RusotoCreateBucketError(e) => rusoto_retryable(e),
+        #[cfg(feature = "s3")]
+        StowError::RusotoListBucketError(e) => rusoto_retryable(e),
+        #[cfg(feature = "s3")]
+        StowError::RusotoPutObjectError(e) => rusoto_retryable(e),
+        #[cfg(feature = "s3")]
+        StowError::RusotoListObjectsError(e) => rusoto_retryable(e),
+        #[cfg(feature = "s3")]
+        StowError::RusotoGetObjectError(e) => rusoto_retryable(e),
+        #[cfg(feature = "s3")]
+        StowError::RusotoDeleteObjectError(e) => rusoto_retryable(e),
+        #[cfg(feature = "s3")]
+        StowError::RusotoDeleteBucketError(e) => rusoto_retryable(e),
+        _ => false,
+    }
+}
+
+/// Check if the http status signals a timeout, throttling or an unavailable server
+fn retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+#[cfg(feature = "s3")]
+/// Check if the request failed on the way or S3 answered with a throttling or server error,
+/// e.g. `SlowDown`, which rusoto doesn't parse into a service error
+fn rusoto_retryable<E>(error: &rusoto_core::RusotoError<E>) -> bool {
+    use rusoto_core::RusotoError;
+
+    match error {
+        RusotoError::HttpDispatch(_) => true,
+        RusotoError::Unknown(res) => {
+            retryable_status(res.status.as_u16())
+                || String::from_utf8_lossy(&res.body).contains("SlowDown")
+        }
+        _ => false,
+    }
+}
+
+/// Location wrapper, which retries operations failing with a retryable error
+/// with an exponential backoff.
+///
+/// The data of new items is buffered in memory, so it can be sent again.
+/// Use `create_item_from` to open the data again for every attempt instead.
+/// Reading items retries the request, but not errors while reading the data.
+#[derive(Clone)]
+pub struct Retry {
+    pub(crate) inner: Arc<Location>,
+    policy: RetryPolicy,
+}
+
+impl Retry {
+    /// Wrap the location, with the default policy
+    pub fn new(inner: Location) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Set how failed operations are retried
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Create the item with the data of a source, which is opened again for every attempt
+    pub async fn create_item_from<R, F, Fut>(
+        &self,
+        container: &str,
+        item: &str,
+        open: F,
+    ) -> Result<()>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
+        F: Fn() -> Fut + Send + Sync,
+        Fut: std::future::Future<Output = Result<R>> + Send,
+    {
+        self.retry(|| async {
+            let reader = open().await?;
+            self.inner.create_item(container, item, reader).await
+        })
+        .await
+    }
+
+    /// Run the operation until it succeeds, fails with an error which isn't retryable
+    /// or the attempts or the deadline are exhausted
+    async fn retry<T, F, Fut>(&self, operation: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let policy = &self.policy;
+        let start = Instant::now();
+        let mut backoff = policy.initial_backoff;
+        let mut attempt = 1;
+
+        loop {
+            let error = match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            if attempt >= policy.max_attempts || !(policy.retryable)(&error) {
+                return Err(error);
+            }
+
+            let delay = if policy.jitter {
+                backoff.mul_f64(fastrand::f64())
+            } else {
+                backoff
+            };
+            if let Some(deadline) = policy.deadline {
+                if start.elapsed() + delay >= deadline {
+                    return Err(error);
+                }
+            }
+            #[cfg(feature = "tracing")]
+            tracing::debug!(attempt, ?delay, error = %error, "retrying stow operation");
+            tokio::time::sleep(delay).await;
+
+            backoff = Duration::from_secs_f64(
+                (backoff.as_secs_f64() * policy.multiplier.max(1.0))
+                    .min(policy.max_backoff.as_secs_f64()),
+            );
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Retry {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.retry(|| self.inner.containers()).await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        self.retry(|| self.inner.create_container(container)).await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        self.retry(|| self.inner.remove_container(container)).await
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        self.retry(|| self.inner.items(container)).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let mut data = vec![];
+        reader.read_to_end(&mut data).await?;
+        let data = bytes::Bytes::from(data);
+
+        self.create_item_from(container, item, || {
+            futures::future::ok(std::io::Cursor::new(data.clone()))
+        })
+        .await
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        self.retry(|| self.inner.read_item(container, item)).await
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        self.retry(|| self.inner.remove_item(container, item)).await
+    }
+}
@@ -1,9 +1,57 @@
 use crate::*;
 
+/// Server-side encryption of items on S3.
+///
+/// A customer key is kept in memory as it is and not zeroized, when it is dropped.
+/// `Debug` is not implemented on purpose, so the key doesn't end up in logs.
+#[derive(Clone)]
+pub enum S3Encryption {
+    /// Keys managed by S3 (SSE-S3)
+    S3,
+    /// Keys managed by KMS, the AWS managed key is used without a key id (SSE-KMS)
+    Kms(Option<String>),
+    /// AES-256 key provided by the customer, which is needed to read the item as well (SSE-C)
+    Customer([u8; 32]),
+}
+
+impl S3Encryption {
+    /// Value of the server-side encryption header
+    fn algorithm(&self) -> Option<String> {
+        match self {
+            S3Encryption::S3 => Some("AES256".to_string()),
+            S3Encryption::Kms(_) => Some("aws:kms".to_string()),
+            S3Encryption::Customer(_) => None,
+        }
+    }
+
+    /// Id of the KMS key
+    fn kms_key_id(&self) -> Option<String> {
+        match self {
+            S3Encryption::Kms(id) => id.clone(),
+            _ => None,
+        }
+    }
+
+    /// Algorithm, key and MD5 digest of the customer key, both base64 encoded
+    fn customer_key(&self) -> (Option<String>, Option<String>, Option<String>) {
+        use md5::Digest;
+
+        match self {
+            S3Encryption::Customer(key) => (
+                Some("AES256".to_string()),
+                Some(base64::encode(key)),
+                Some(base64::encode(md5::Md5::digest(key))),
+            ),
+            _ => (None, None, None),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct S3 {
     region: rusoto_core::region::Region,
     credentials: rusoto_credential::StaticProvider,
+    encryption: Option<S3Encryption>,
 }
 
 impl S3 {
@@ -14,9 +62,16 @@ impl S3 {
                 access_key.into(),
                 secret_key.into(),
             ),
+            encryption: None,
         })
     }
 
+    /// Encrypt created items on the server by default, customer keys are used to read items as well
+    pub fn with_encryption(mut self, encryption: S3Encryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
     fn create_client(&self) -> Result<rusoto_s3::S3Client> {
         Ok(rusoto_s3::S3Client::new_with(
             rusoto_core::request::HttpClient::new()?,
@@ -24,11 +79,79 @@ impl S3 {
             self.region.clone(),
         ))
     }
+
+    /// Create the item with the server-side encryption, instead of the default one
+    pub async fn create_item_with_encryption(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
+        encryption: Option<&S3Encryption>,
+    ) -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let client = self.create_client()?;
+
+        // read the full file into memory to have the content-length
+        // todo: this needs to be improved
+        let mut data = vec![];
+        reader.read_to_end(&mut data).await?;
+
+        // create rusoto byte stream
+        let size = data.len() as i64;
+        let stream = rusoto_s3::StreamingBody::from(data);
+
+        let (sse_customer_algorithm, sse_customer_key, sse_customer_key_md5) =
+            encryption.map(|e| e.customer_key()).unwrap_or_default();
+
+        let req = rusoto_s3::PutObjectRequest {
+            bucket: container.to_string(),
+            body: Some(stream),
+            key: item.to_string(),
+            content_length: Some(size),
+            server_side_encryption: encryption.and_then(|e| e.algorithm()),
+            ssekms_key_id: encryption.and_then(|e| e.kms_key_id()),
+            sse_customer_algorithm,
+            sse_customer_key,
+            sse_customer_key_md5,
+            ..Default::default()
+        };
+
+        rusoto_s3::S3::put_object(&client, req).await?;
+        Ok(())
+    }
+
+    /// Read the item with the customer key of the server-side encryption, instead of the default one
+    pub async fn read_item_with_encryption(
+        &self,
+        container: &str,
+        item: &str,
+        encryption: Option<&S3Encryption>,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let client = self.create_client()?;
+
+        let (sse_customer_algorithm, sse_customer_key, sse_customer_key_md5) =
+            encryption.map(|e| e.customer_key()).unwrap_or_default();
+
+        let req = rusoto_s3::GetObjectRequest {
+            bucket: container.to_string(),
+            key: item.to_string(),
+            sse_customer_algorithm,
+            sse_customer_key,
+            sse_customer_key_md5,
+            ..Default::default()
+        };
+
+        let res = rusoto_s3::S3::get_object(&client, req).await?;
+        let res = res.body.ok_or(StowError::EmptyItemError)?;
+
+        Ok(Box::new(res.into_async_read()))
+    }
 }
 
 #[async_trait::async_trait]
 impl Adapter for S3 {
-    async fn containers(&mut self) -> Result<Vec<String>> {
+    async fn containers(&self) -> Result<Vec<String>> {
         let client = self.create_client()?;
 
         let res = rusoto_s3::S3::list_buckets(&client).await?;
@@ -41,7 +164,7 @@ impl Adapter for S3 {
             .collect())
     }
 
-    async fn create_container(&mut self, container: &str) -> Result<()> {
+    async fn create_container(&self, container: &str) -> Result<()> {
         let client = self.create_client()?;
 
         let bucket_config = rusoto_s3::CreateBucketConfiguration {
@@ -71,7 +194,7 @@ impl Adapter for S3 {
         Ok(())
     }
 
-    async fn remove_container(&mut self, container: &str) -> Result<()> {
+    async fn remove_container(&self, container: &str) -> Result<()> {
         let client = self.create_client()?;
 
         let req = rusoto_s3::DeleteBucketRequest {
@@ -83,7 +206,7 @@ impl Adapter for S3 {
         Ok(())
     }
 
-    async fn items(&mut self, container: &str) -> Result<Vec<String>> {
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
         let client = self.create_client()?;
 
         // inital request
@@ -123,56 +246,25 @@ impl Adapter for S3 {
     }
 
     async fn create_item(
-        &mut self,
+        &self,
         container: &str,
         item: &str,
-        mut reader: impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
+        reader: impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
     ) -> Result<()> {
-        use tokio::io::AsyncReadExt;
-
-        let client = self.create_client()?;
-
-        // read the full file into memory to have the content-length
-        // todo: this needs to be improved
-        let mut data = vec![];
-        reader.read_to_end(&mut data).await?;
-
-        // create rusoto byte stream
-        let size = data.len() as i64;
-        let stream = rusoto_s3::StreamingBody::from(data);
-
-        let req = rusoto_s3::PutObjectRequest {
-            bucket: container.to_string(),
-            body: Some(stream),
-            key: item.to_string(),
-            content_length: Some(size),
-            ..Default::default()
-        };
-
-        rusoto_s3::S3::put_object(&client, req).await?;
-        Ok(())
+        self.create_item_with_encryption(container, item, reader, self.encryption.as_ref())
+            .await
     }
 
     async fn read_item(
-        &mut self,
+        &self,
         container: &str,
         item: &str,
     ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
-        let client = self.create_client()?;
-
-        let req = rusoto_s3::GetObjectRequest {
-            bucket: container.to_string(),
-            key: item.to_string(),
-            ..Default::default()
-        };
-
-        let res = rusoto_s3::S3::get_object(&client, req).await?;
-        let res = res.body.ok_or(StowError::EmptyItemError)?;
-
-        Ok(Box::new(res.into_async_read()))
+        self.read_item_with_encryption(container, item, self.encryption.as_ref())
+            .await
     }
 
-    async fn remove_item(&mut self, container: &str, item: &str) -> Result<()> {
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
         let client = self.create_client()?;
 
         let req = rusoto_s3::DeleteObjectRequest {
@@ -185,3 +277,42 @@ impl Adapter for S3 {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encryption_headers() {
+        let encryption = S3Encryption::S3;
+        assert_eq!(encryption.algorithm().as_deref(), Some("AES256"));
+        assert_eq!(encryption.kms_key_id(), None);
+        assert_eq!(encryption.customer_key(), (None, None, None));
+
+        let encryption = S3Encryption::Kms(None);
+        assert_eq!(encryption.algorithm().as_deref(), Some("aws:kms"));
+        assert_eq!(encryption.kms_key_id(), None);
+        assert_eq!(encryption.customer_key(), (None, None, None));
+
+        let encryption = S3Encryption::Kms(Some("alias/stow".to_string()));
+        assert_eq!(encryption.algorithm().as_deref(), Some("aws:kms"));
+        assert_eq!(encryption.kms_key_id().as_deref(), Some("alias/stow"));
+        assert_eq!(encryption.customer_key(), (None, None, None));
+
+        let mut key = [0; 32];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let encryption = S3Encryption::Customer(key);
+        assert_eq!(encryption.algorithm(), None);
+        assert_eq!(encryption.kms_key_id(), None);
+        assert_eq!(
+            encryption.customer_key(),
+            (
+                Some("AES256".to_string()),
+                Some("AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=".to_string()),
+                Some("tP/LI3N87DFaSk0aoqYgzg==".to_string()),
+            )
+        );
+    }
+}
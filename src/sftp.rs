@@ -0,0 +1,174 @@
+use crate::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Authentication method for the SFTP server
+#[derive(Clone)]
+pub enum SftpAuth {
+    Password(String),
+    /// Path to the private key file, with an optional passphrase for it
+    Key {
+        private_key: String,
+        passphrase: Option<String>,
+    },
+}
+
+/// Location on a SFTP server.
+/// Containers are the top-level directories under the root, items are the files in them.
+#[derive(Clone)]
+pub struct Sftp {
+    sftp: Arc<ssh2::Sftp>,
+    root: PathBuf,
+}
+
+impl Sftp {
+    /// Connect to the SFTP server at the given address, e.g. `example.com:22`.
+    /// The root directory gets created if it is not available.
+    pub async fn new(address: &str, user: &str, auth: SftpAuth, root: &str) -> Result<Self> {
+        let address = address.to_string();
+        let user = user.to_string();
+        let root = PathBuf::from(root);
+
+        let sftp = {
+            let root = root.clone();
+            tokio::task::spawn_blocking(move || -> Result<ssh2::Sftp> {
+                let mut session = ssh2::Session::new()?;
+                session.set_tcp_stream(std::net::TcpStream::connect(&address)?);
+                session.handshake()?;
+
+                match &auth {
+                    SftpAuth::Password(password) => session.userauth_password(&user, password)?,
+                    SftpAuth::Key {
+                        private_key,
+                        passphrase,
+                    } => session.userauth_pubkey_file(
+                        &user,
+                        None,
+                        Path::new(private_key),
+                        passphrase.as_deref(),
+                    )?,
+                }
+
+                let sftp = session.sftp()?;
+                if sftp.stat(&root).is_err() {
+                    sftp.mkdir(&root, 0o755)?;
+                }
+                Ok(sftp)
+            })
+            .await??
+        };
+
+        Ok(Self {
+            sftp: Arc::new(sftp),
+            root,
+        })
+    }
+
+    /// The blocking SFTP calls are run on the blocking thread pool
+    async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&ssh2::Sftp, &Path) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let sftp = self.sftp.clone();
+        let root = self.root.clone();
+        tokio::task::spawn_blocking(move || f(&sftp, &root)).await?
+    }
+
+    /// List the names of the entries in the given directory
+    fn list(sftp: &ssh2::Sftp, path: &Path, dirs: bool) -> Result<Vec<String>> {
+        Ok(sftp
+            .readdir(path)?
+            .into_iter()
+            .filter(|(_, stat)| stat.is_dir() == dirs)
+            .filter_map(|(p, _)| Some(p.file_name()?.to_str()?.to_string()))
+            .collect())
+    }
+
+    /// Remove the directory with all its content
+    fn remove_dir_all(sftp: &ssh2::Sftp, path: &Path) -> Result<()> {
+        for (p, stat) in sftp.readdir(path)? {
+            if stat.is_dir() {
+                Self::remove_dir_all(sftp, &p)?;
+            } else {
+                sftp.unlink(&p)?;
+            }
+        }
+        sftp.rmdir(path)?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Sftp {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.run(|sftp, root| Self::list(sftp, root, true)).await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        let container = container.to_string();
+
+        self.run(move |sftp, root| {
+            let path = root.join(container);
+
+            // only create the directory if not available
+            if sftp.stat(&path).is_err() {
+                sftp.mkdir(&path, 0o755)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        let container = util::streamline(container);
+
+        self.run(move |sftp, root| Self::remove_dir_all(sftp, &root.join(container)))
+            .await
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let container = util::streamline(container);
+
+        self.run(move |sftp, root| Self::list(sftp, &root.join(container), false))
+            .await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        let item = util::streamline_item(item)?;
+        let container = container.to_string();
+
+        let file = self
+            .run(move |sftp, root| Ok(sftp.create(&root.join(container).join(item))?))
+            .await?;
+        util::copy_to_blocking(&mut reader, file).await?;
+
+        Ok(())
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let file = self
+            .run(move |sftp, root| Ok(sftp.open(root.join(container).join(item))?))
+            .await?;
+        Ok(util::blocking_reader(file))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        self.run(move |sftp, root| Ok(sftp.unlink(&root.join(container).join(item))?))
+            .await
+    }
+}
@@ -0,0 +1,368 @@
+use crate::*;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Default size of the chunks items are stored in
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Location in a single SQLite database file.
+/// Containers, items and the chunks of their data are stored in tables,
+/// every write operation runs in its own transaction.
+/// New items are staged first, so the write lock is only held briefly to replace the old item.
+///
+/// Every operation opens its own connection, to allow concurrent readers next to a writer.
+#[derive(Debug, Clone)]
+pub struct Sqlite {
+    path: String,
+    chunk_size: usize,
+}
+
+impl Sqlite {
+    /// Open the database at the given path, it gets created if not available
+    pub async fn new(path: &str) -> Result<Self> {
+        let this = Self {
+            path: path.to_string(),
+            chunk_size: CHUNK_SIZE,
+        };
+
+        this.blocking(|con| {
+            // the write-ahead log allows reading while writing
+            con.query_row("PRAGMA journal_mode = WAL", params![], |_| Ok(()))?;
+            con.execute_batch(
+                "CREATE TABLE IF NOT EXISTS containers (
+                    name TEXT PRIMARY KEY
+                );
+                CREATE TABLE IF NOT EXISTS items (
+                    container TEXT NOT NULL REFERENCES containers (name) ON DELETE CASCADE,
+                    name TEXT NOT NULL,
+                    size INTEGER NOT NULL,
+                    PRIMARY KEY (container, name)
+                );
+                CREATE TABLE IF NOT EXISTS chunks (
+                    container TEXT NOT NULL,
+                    item TEXT NOT NULL,
+                    number INTEGER NOT NULL,
+                    data BLOB NOT NULL,
+                    PRIMARY KEY (container, item, number),
+                    FOREIGN KEY (container, item) REFERENCES items (container, name) ON DELETE CASCADE
+                );",
+            )?;
+            Ok(())
+        })
+        .await?;
+
+        Ok(this)
+    }
+
+    /// Set the size of the chunks new items are stored in
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Run a blocking operation with a new connection on the blocking thread pool
+    async fn blocking<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || f(&mut connect(&path)?)).await?
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Sqlite {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.blocking(|con| {
+            let mut stmt = con.prepare("SELECT name FROM containers ORDER BY name")?;
+            let names = stmt
+                .query_map(params![], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok(names)
+        })
+        .await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        let container = container.to_string();
+
+        self.blocking(move |con| {
+            let tx = write_transaction(con)?;
+            tx.execute(
+                "INSERT OR IGNORE INTO containers (name) VALUES (?)",
+                params![container],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        let container = util::streamline(container);
+
+        // the items and chunks are removed by the foreign keys
+        self.blocking(move |con| {
+            let tx = write_transaction(con)?;
+            if tx.execute("DELETE FROM containers WHERE name = ?", params![container])? == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let container = util::streamline(container);
+
+        // staged items of running uploads have no file ending
+        self.blocking(move |con| {
+            let mut stmt = con.prepare(
+                "SELECT name FROM items WHERE container = ? AND instr(name, '.') > 0 ORDER BY name",
+            )?;
+            let names = stmt
+                .query_map(params![container], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok(names)
+        })
+        .await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        use futures::SinkExt;
+        use tokio::io::AsyncReadExt;
+
+        let container = container.to_string();
+        let item = util::streamline_item(item)?;
+        let staged = staged_name();
+
+        // the chunks are staged under another name on the blocking thread pool while reading,
+        // each in its own short transaction, and moved to the item once all data is received.
+        // Without the end of the data, e.g. on an error of the reader or when this future got dropped,
+        // the staged chunks are removed and the item is kept as it is
+        let (mut send, mut recv) =
+            futures::channel::mpsc::channel::<std::io::Result<Option<Vec<u8>>>>(2);
+
+        let write = self.blocking(move |con| {
+            let exists: Option<i64> = con
+                .query_row(
+                    "SELECT 1 FROM containers WHERE name = ?",
+                    params![container],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if exists.is_none() {
+                return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+            }
+
+            con.execute(
+                "INSERT INTO items (container, name, size) VALUES (?, ?, 0)",
+                params![container, staged],
+            )?;
+
+            let res = stage_chunks(con, &container, &staged, &mut recv).and_then(|size| {
+                let tx = write_transaction(con)?;
+                tx.execute(
+                    "DELETE FROM items WHERE container = ? AND name = ?",
+                    params![container, item],
+                )?;
+                tx.execute(
+                    "INSERT INTO items (container, name, size) VALUES (?, ?, ?)",
+                    params![container, item, size],
+                )?;
+                tx.execute(
+                    "UPDATE chunks SET item = ? WHERE container = ? AND item = ?",
+                    params![item, container, staged],
+                )?;
+                tx.execute(
+                    "DELETE FROM items WHERE container = ? AND name = ?",
+                    params![container, staged],
+                )?;
+                tx.commit()?;
+                Ok(())
+            });
+
+            // the staged chunks are removed by the foreign key
+            if res.is_err() {
+                let _ = con.execute(
+                    "DELETE FROM items WHERE container = ? AND name = ?",
+                    params![container, staged],
+                );
+            }
+            res
+        });
+
+        let read = async move {
+            loop {
+                let mut chunk = Vec::with_capacity(self.chunk_size);
+                let res = (&mut reader)
+                    .take(self.chunk_size as u64)
+                    .read_to_end(&mut chunk)
+                    .await;
+
+                // the end of the data is sent explicitly, to tell it apart from an aborted upload
+                let (res, done) = match res {
+                    Ok(0) => (Ok(None), true),
+                    Ok(_) => (Ok(Some(chunk)), false),
+                    Err(e) => (Err(e), true),
+                };
+
+                // stop at the end, on errors or when the writer failed, its error is returned
+                if send.send(res).await.is_err() || done {
+                    break;
+                }
+            }
+        };
+
+        let (res, _) = futures::join!(write, read);
+        res
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        // fail early, instead of on the first read
+        let (c, i) = (container.clone(), item.clone());
+        if !self
+            .blocking(move |con| Ok(item_exists(con, &c, &i)?))
+            .await?
+        {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+        }
+
+        // the chunks are streamed within one read transaction, to see a consistent item
+        let path = self.path.clone();
+        Ok(util::blocking_stream(move |writer| {
+            let mut con = connect(&path).map_err(std::io::Error::other)?;
+            let tx = con.transaction().map_err(std::io::Error::other)?;
+
+            if !item_exists(&tx, &container, &item).map_err(std::io::Error::other)? {
+                return Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+            }
+            let rows = chunk_rows(&tx, &container, &item).map_err(std::io::Error::other)?;
+
+            for row in rows {
+                let mut blob = tx
+                    .blob_open(rusqlite::DatabaseName::Main, "chunks", "data", row, true)
+                    .map_err(std::io::Error::other)?;
+                std::io::copy(&mut blob, writer)?;
+            }
+            Ok(())
+        }))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        // the chunks are removed by the foreign key
+        self.blocking(move |con| {
+            let tx = write_transaction(con)?;
+            let removed = tx.execute(
+                "DELETE FROM items WHERE container = ? AND name = ?",
+                params![container, item],
+            )?;
+            if removed == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Open a new connection to the database
+fn connect(path: &str) -> rusqlite::Result<Connection> {
+    let con = Connection::open(path)?;
+    con.busy_timeout(std::time::Duration::from_secs(30))?;
+    con.execute_batch("PRAGMA foreign_keys = ON;")?;
+    Ok(con)
+}
+
+/// Unique name of the item new chunks are staged under.
+/// Names of items always have a file ending, so staged ones don't have one
+fn staged_name() -> String {
+    static UPLOADS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let upload = UPLOADS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    format!("upload-{}-{}-{}", std::process::id(), nanos, upload)
+}
+
+/// Write the received chunks to the staged item, each in its own transaction, and return their size.
+/// The data ends with `None`, a closed channel without it means the upload was aborted
+fn stage_chunks(
+    con: &Connection,
+    container: &str,
+    staged: &str,
+    recv: &mut futures::channel::mpsc::Receiver<std::io::Result<Option<Vec<u8>>>>,
+) -> Result<i64> {
+    use futures::StreamExt;
+
+    let mut size = 0;
+    let mut number = 0;
+    loop {
+        match futures::executor::block_on(recv.next()) {
+            Some(Ok(Some(chunk))) => {
+                con.execute(
+                    "INSERT INTO chunks (container, item, number, data) VALUES (?, ?, ?, ?)",
+                    params![container, staged, number, chunk],
+                )?;
+                size += chunk.len() as i64;
+                number += 1;
+            }
+            Some(Ok(None)) => return Ok(size),
+            Some(Err(e)) => return Err(e.into()),
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Interrupted,
+                    "the upload was aborted",
+                )
+                .into())
+            }
+        }
+    }
+}
+
+/// Start a write transaction, which takes the write lock right away.
+/// A deferred one fails without waiting for the busy timeout,
+/// if another connection writes when it upgrades from reading to writing.
+fn write_transaction(con: &mut Connection) -> rusqlite::Result<rusqlite::Transaction<'_>> {
+    con.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)
+}
+
+/// Check if the item is available
+fn item_exists(con: &Connection, container: &str, item: &str) -> rusqlite::Result<bool> {
+    let exists: Option<i64> = con
+        .query_row(
+            "SELECT 1 FROM items WHERE container = ? AND name = ?",
+            params![container, item],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(exists.is_some())
+}
+
+/// Get the row ids of the chunks of the item in their order
+fn chunk_rows(con: &Connection, container: &str, item: &str) -> rusqlite::Result<Vec<i64>> {
+    let mut stmt =
+        con.prepare("SELECT rowid FROM chunks WHERE container = ? AND item = ? ORDER BY number")?;
+    let rows = stmt
+        .query_map(params![container, item], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+    Ok(rows)
+}
@@ -0,0 +1,439 @@
+use crate::*;
+use std::sync::Arc;
+
+/// Default size of the segments large items get uploaded in
+const SEGMENT_SIZE: usize = 64 * 1024 * 1024;
+
+/// Suffix of the containers, which hold the segments of large items
+const SEGMENTS_SUFFIX: &str = "_segments";
+
+/// Keystone v3 password credentials
+#[derive(Debug, Clone)]
+pub struct SwiftAuth {
+    /// The Keystone identity url, e.g. `https://keystone.example.com/v3`
+    pub auth_url: String,
+    pub user: String,
+    pub password: String,
+    /// The domain of the user and project, mostly `Default`
+    pub domain: String,
+    pub project: String,
+    /// The region of the object store endpoint, the first one is used if not set
+    pub region: Option<String>,
+}
+
+/// Token and object storage url, which are received on authentication
+struct Session {
+    token: String,
+    url: reqwest::Url,
+}
+
+/// Location on an OpenStack Swift object store.
+/// Items bigger than the segment size are uploaded as static large objects (SLO).
+#[derive(Clone)]
+pub struct Swift {
+    client: reqwest::Client,
+    auth: SwiftAuth,
+    session: Arc<tokio::sync::RwLock<Session>>,
+    segment_size: usize,
+}
+
+impl Swift {
+    pub async fn new(auth: SwiftAuth) -> Result<Self> {
+        let client = reqwest::Client::new();
+        let session = Self::authenticate(&client, &auth).await?;
+
+        Ok(Self {
+            client,
+            auth,
+            session: Arc::new(tokio::sync::RwLock::new(session)),
+            segment_size: SEGMENT_SIZE,
+        })
+    }
+
+    /// Set the size of the segments, items bigger than it are uploaded as large objects
+    pub fn with_segment_size(mut self, segment_size: usize) -> Self {
+        self.segment_size = segment_size.max(1);
+        self
+    }
+
+    /// Request a token from Keystone and find the object store in its catalog
+    async fn authenticate(client: &reqwest::Client, auth: &SwiftAuth) -> Result<Session> {
+        let body = serde_json::json!({
+            "auth": {
+                "identity": {
+                    "methods": ["password"],
+                    "password": {
+                        "user": {
+                            "name": auth.user,
+                            "domain": { "name": auth.domain },
+                            "password": auth.password,
+                        }
+                    }
+                },
+                "scope": {
+                    "project": {
+                        "name": auth.project,
+                        "domain": { "name": auth.domain },
+                    }
+                }
+            }
+        });
+
+        let res = client
+            .post(format!(
+                "{}/auth/tokens",
+                auth.auth_url.trim_end_matches('/')
+            ))
+            .json(&body)
+            .send()
+            .await?;
+        let res = util::http_status(res)?;
+
+        let token = res
+            .headers()
+            .get("x-subject-token")
+            .and_then(|t| t.to_str().ok())
+            .ok_or(StowError::SwiftCatalogError)?
+            .to_string();
+        let body: serde_json::Value = res.json().await?;
+
+        // find the public endpoint of the object store
+        let url = body["token"]["catalog"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|s| s["type"] == "object-store")
+            .flat_map(|s| s["endpoints"].as_array().into_iter().flatten())
+            .filter(|e| e["interface"] == "public")
+            .find(|e| match &auth.region {
+                Some(region) => e["region_id"] == *region || e["region"] == *region,
+                None => true,
+            })
+            .and_then(|e| e["url"].as_str())
+            .ok_or(StowError::SwiftCatalogError)?;
+
+        Ok(Session {
+            token,
+            url: reqwest::Url::parse(url)?,
+        })
+    }
+
+    /// Send a request against the given path segments, relative to the storage url.
+    /// The request is repeated once with a new token, if the current one expired.
+    /// The body is `Bytes`, so repeating the request doesn't copy it.
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        path: &[&str],
+        query: &[(&str, &str)],
+        body: bytes::Bytes,
+    ) -> Result<reqwest::Response> {
+        let mut retry = true;
+
+        loop {
+            let (token, mut url) = {
+                let session = self.session.read().await;
+                (session.token.clone(), session.url.clone())
+            };
+            url.path_segments_mut()
+                .map_err(|_| StowError::Unknown)?
+                .pop_if_empty()
+                .extend(path);
+            url.query_pairs_mut().extend_pairs(query);
+
+            let res = self
+                .client
+                .request(method.clone(), url)
+                .header("x-auth-token", &token)
+                .body(body.clone())
+                .send()
+                .await?;
+
+            if res.status() == reqwest::StatusCode::UNAUTHORIZED && retry {
+                retry = false;
+
+                // only authenticate again, if no other task did it in the meantime
+                let mut session = self.session.write().await;
+                if session.token == token {
+                    *session = Self::authenticate(&self.client, &self.auth).await?;
+                }
+                continue;
+            }
+
+            return util::http_status(res);
+        }
+    }
+
+    /// Request a paged json listing and collect all names of it
+    async fn list(&self, path: &[&str]) -> Result<Vec<String>> {
+        let mut names: Vec<String> = vec![];
+
+        loop {
+            let mut query = vec![("format", "json")];
+            if let Some(marker) = names.last() {
+                query.push(("marker", marker));
+            }
+
+            let res = self
+                .send(reqwest::Method::GET, path, &query, bytes::Bytes::new())
+                .await?;
+            // an empty container responds without content
+            if res.status() == reqwest::StatusCode::NO_CONTENT {
+                return Ok(names);
+            }
+
+            let page: Vec<serde_json::Value> = res.json().await?;
+            if page.is_empty() {
+                return Ok(names);
+            }
+
+            names.extend(
+                page.iter()
+                    .filter_map(|e| e["name"].as_str())
+                    .map(|n| n.to_string()),
+            );
+        }
+    }
+    /// Upload the item as one object or as large object, depending on its size
+    async fn upload(
+        &self,
+        container: &str,
+        item: &str,
+        mut reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let mut segment = Vec::with_capacity(self.segment_size);
+        (&mut reader)
+            .take(self.segment_size as u64)
+            .read_to_end(&mut segment)
+            .await?;
+
+        // small items are uploaded as one object
+        let mut next = vec![];
+        (&mut reader).take(1).read_to_end(&mut next).await?;
+        if next.is_empty() {
+            self.send(
+                reqwest::Method::PUT,
+                &[container, item],
+                &[],
+                segment.into(),
+            )
+            .await?;
+            return Ok(());
+        }
+
+        // large items are uploaded in segments into a separate container
+        let segments = format!("{}{}", container, SEGMENTS_SUFFIX);
+        self.send(reqwest::Method::PUT, &[&segments], &[], bytes::Bytes::new())
+            .await?;
+
+        // every upload gets its own prefix, to not overwrite the segments of the current item
+        let upload = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|_| StowError::Unknown)?
+            .as_nanos()
+            .to_string();
+
+        let mut manifest = vec![];
+        loop {
+            let name = format!("{}/{}/{:08}", item, upload, manifest.len());
+            let size = segment.len();
+            self.send(
+                reqwest::Method::PUT,
+                &[&segments, &name],
+                &[],
+                segment.into(),
+            )
+            .await?;
+            manifest.push(serde_json::json!({
+                "path": format!("/{}/{}", segments, name),
+                "size_bytes": size,
+            }));
+
+            // the next segment starts with the already read byte
+            segment = std::mem::take(&mut next);
+            (&mut reader)
+                .take((self.segment_size - segment.len()) as u64)
+                .read_to_end(&mut segment)
+                .await?;
+            if segment.is_empty() {
+                break;
+            }
+        }
+
+        self.send(
+            reqwest::Method::PUT,
+            &[container, item],
+            &[("multipart-manifest", "put")],
+            serde_json::to_vec(&manifest)?.into(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Get the paths of the segments, when the item is a large object
+    async fn segments(&self, container: &str, item: &str) -> Result<Vec<String>> {
+        let res = match self
+            .send(
+                reqwest::Method::HEAD,
+                &[container, item],
+                &[],
+                bytes::Bytes::new(),
+            )
+            .await
+        {
+            Err(StowError::HttpStatusError(404)) => return Ok(vec![]),
+            res => res?,
+        };
+        if !res.headers().contains_key("x-static-large-object") {
+            return Ok(vec![]);
+        }
+
+        let res = self
+            .send(
+                reqwest::Method::GET,
+                &[container, item],
+                &[("multipart-manifest", "get")],
+                bytes::Bytes::new(),
+            )
+            .await?;
+        let manifest: Vec<serde_json::Value> = res.json().await?;
+
+        Ok(manifest
+            .iter()
+            .filter_map(|s| s["name"].as_str())
+            .map(|s| s.to_string())
+            .collect())
+    }
+
+    /// Remove the segment with the given path, which starts with its container
+    async fn remove_segment(&self, path: &str) -> Result<()> {
+        let path = path.trim_start_matches('/');
+        let (container, name) = path.split_once('/').ok_or(StowError::Unknown)?;
+
+        match self
+            .send(
+                reqwest::Method::DELETE,
+                &[container, name],
+                &[],
+                bytes::Bytes::new(),
+            )
+            .await
+        {
+            Err(StowError::HttpStatusError(404)) => Ok(()),
+            res => res.map(|_| ()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Swift {
+    async fn containers(&self) -> Result<Vec<String>> {
+        Ok(self
+            .list(&[])
+            .await?
+            .into_iter()
+            .filter(|c| !c.ends_with(SEGMENTS_SUFFIX))
+            .collect())
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        self.send(reqwest::Method::PUT, &[container], &[], bytes::Bytes::new())
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        let container = util::streamline(container);
+
+        // containers need to be empty to be removed
+        for item in self.items(&container).await? {
+            self.remove_item(&container, &item).await?;
+        }
+        self.send(
+            reqwest::Method::DELETE,
+            &[&container],
+            &[],
+            bytes::Bytes::new(),
+        )
+        .await?;
+
+        // remove the segments container, if large items were stored
+        let segments = format!("{}{}", container, SEGMENTS_SUFFIX);
+        let names = match self.list(&[&segments]).await {
+            Err(StowError::HttpStatusError(404)) => return Ok(()),
+            names => names?,
+        };
+        for name in names {
+            self.remove_segment(&format!("{}/{}", segments, name))
+                .await?;
+        }
+        self.send(
+            reqwest::Method::DELETE,
+            &[&segments],
+            &[],
+            bytes::Bytes::new(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let container = util::streamline(container);
+
+        self.list(&[&container]).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    ) -> Result<()> {
+        let item = util::streamline_item(item)?;
+
+        // the segments of a replaced large item are not removed by swift
+        let replaced = self.segments(container, &item).await?;
+        self.upload(container, &item, reader).await?;
+        for segment in replaced {
+            self.remove_segment(&segment).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let res = self
+            .send(
+                reqwest::Method::GET,
+                &[&container, &item],
+                &[],
+                bytes::Bytes::new(),
+            )
+            .await?;
+        Ok(util::response_reader(res))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        // removes the segments of large items as well, normal items are not affected by it
+        self.send(
+            reqwest::Method::DELETE,
+            &[&container, &item],
+            &[("multipart-manifest", "delete")],
+            bytes::Bytes::new(),
+        )
+        .await?;
+        Ok(())
+    }
+}
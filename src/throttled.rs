@@ -0,0 +1,186 @@
+use crate::*;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token bucket, which holds up to one second of tokens.
+/// Tokens can be taken in advance, the next user waits until they are refilled.
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rate: f64) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            rate,
+            tokens: rate,
+            updated: Instant::now(),
+        }))
+    }
+
+    /// Take the tokens and return how long to wait until the bucket isn't empty anymore
+    fn take(bucket: &Mutex<Self>, amount: f64) -> Duration {
+        let mut bucket = bucket.lock().unwrap_or_else(|e| e.into_inner());
+
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * bucket.rate;
+        bucket.tokens = (bucket.tokens + refill).min(bucket.rate) - amount;
+        bucket.updated = now;
+
+        if bucket.tokens >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / bucket.rate)
+        }
+    }
+}
+
+/// Location wrapper, which limits the operations per second and the bytes per second
+/// read from items and written to them.
+///
+/// The budgets are shared by all clones of the wrapper, e.g. by all tasks of a migration.
+#[derive(Clone)]
+pub struct Throttled {
+    pub(crate) inner: Arc<Location>,
+    operations: Option<Arc<Mutex<Bucket>>>,
+    bytes: Option<Arc<Mutex<Bucket>>>,
+}
+
+impl Throttled {
+    /// Wrap the location, without any limits yet
+    pub fn new(inner: Location) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            operations: None,
+            bytes: None,
+        }
+    }
+
+    /// Limit the number of operations per second, which must be a positive number
+    pub fn with_operations_per_second(mut self, operations: f64) -> Result<Self> {
+        if !(operations > 0.0 && operations.is_finite()) {
+            return Err(StowError::InvalidRateError(operations));
+        }
+        self.operations = Some(Bucket::new(operations));
+        Ok(self)
+    }
+
+    /// Limit the bytes per second, which are read from items and written to them together.
+    /// The limit must not be zero
+    pub fn with_bytes_per_second(mut self, bytes: u64) -> Result<Self> {
+        if bytes == 0 {
+            return Err(StowError::InvalidRateError(0.0));
+        }
+        self.bytes = Some(Bucket::new(bytes as f64));
+        Ok(self)
+    }
+
+    /// Wait until the next operation is allowed
+    async fn operation(&self) {
+        if let Some(operations) = &self.operations {
+            let wait = Bucket::take(operations, 1.0);
+            if wait > Duration::from_secs(0) {
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+
+    /// Limit the bytes per second of the reader
+    fn reader(
+        &self,
+        reader: Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>,
+    ) -> Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync> {
+        match &self.bytes {
+            Some(bytes) => Box::new(ThrottledReader {
+                reader,
+                bytes: bytes.clone(),
+                wait: None,
+            }),
+            None => reader,
+        }
+    }
+}
+
+/// Reader, which takes the read bytes from the bucket and waits before the next read,
+/// if they exceeded the budget
+struct ThrottledReader {
+    reader: Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>,
+    bytes: Arc<Mutex<Bucket>>,
+    wait: Option<std::pin::Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl tokio::io::AsyncRead for ThrottledReader {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        if let Some(wait) = &mut self.wait {
+            futures::ready!(std::future::Future::poll(wait.as_mut(), cx));
+            self.wait = None;
+        }
+
+        let before = buf.filled().len();
+        futures::ready!(std::pin::Pin::new(&mut self.reader).poll_read(cx, buf))?;
+
+        let read = buf.filled().len() - before;
+        if read > 0 {
+            let wait = Bucket::take(&self.bytes, read as f64);
+            if wait > Duration::from_secs(0) {
+                self.wait = Some(Box::pin(tokio::time::sleep(wait)));
+            }
+        }
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for Throttled {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.operation().await;
+        self.inner.containers().await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        self.operation().await;
+        self.inner.create_container(container).await
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        self.operation().await;
+        self.inner.remove_container(container).await
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        self.operation().await;
+        self.inner.items(container).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        reader: impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
+    ) -> Result<()> {
+        self.operation().await;
+
+        // boxed, so wrapped locations don't nest the reader types endlessly
+        let reader = self.reader(Box::new(reader));
+        self.inner.create_item(container, item, reader).await
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        self.operation().await;
+        Ok(self.reader(self.inner.read_item(container, item).await?))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        self.operation().await;
+        self.inner.remove_item(container, item).await
+    }
+}
@@ -0,0 +1,206 @@
+use crate::*;
+
+/// Authentication method for the WebDAV server
+#[derive(Clone)]
+pub enum WebDavAuth {
+    None,
+    Basic { user: String, password: String },
+    Bearer(String),
+}
+
+/// Location on a WebDAV server, like Nextcloud or ownCloud.
+/// Containers are the collections under the base url, items are the files in them.
+#[derive(Clone)]
+pub struct WebDav {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    auth: WebDavAuth,
+}
+
+impl WebDav {
+    /// Create a new WebDAV connection with the given base url,
+    /// e.g. `https://cloud.example.com/remote.php/dav/files/user/stow`
+    pub async fn new(url: &str, auth: WebDavAuth) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url: reqwest::Url::parse(url)?,
+            auth,
+        })
+    }
+
+    /// Create the url of the given path segments, relative to the base url
+    fn url(&self, path: &[&str]) -> Result<reqwest::Url> {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| StowError::Unknown)?
+            .pop_if_empty()
+            .extend(path);
+        Ok(url)
+    }
+
+    /// Create a request against the given path segments, relative to the base url
+    fn request(&self, method: &str, path: &[&str]) -> Result<reqwest::RequestBuilder> {
+        let method =
+            reqwest::Method::from_bytes(method.as_bytes()).map_err(|_| StowError::Unknown)?;
+        let req = self.client.request(method, self.url(path)?);
+
+        Ok(match &self.auth {
+            WebDavAuth::None => req,
+            WebDavAuth::Basic { user, password } => req.basic_auth(user, Some(password)),
+            WebDavAuth::Bearer(token) => req.bearer_auth(token),
+        })
+    }
+
+    /// List the names of the direct children of the collection
+    async fn list(&self, path: &[&str], collections: bool) -> Result<Vec<String>> {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?><d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/></d:prop></d:propfind>"#;
+
+        let res = self
+            .request("PROPFIND", path)?
+            .header("Depth", "1")
+            .header(reqwest::header::CONTENT_TYPE, "application/xml")
+            .body(body)
+            .send()
+            .await?;
+        let xml = util::http_status(res)?.text().await?;
+
+        // skip the entry of the listed collection itself
+        let url = self.url(path)?;
+        let mut names = vec![];
+        for (href, collection) in Self::parse_multistatus(&xml)? {
+            // the href can be an absolute url or a path
+            let href = url.join(&href)?;
+            if collection != collections
+                || href.path().trim_end_matches('/') == url.path().trim_end_matches('/')
+            {
+                continue;
+            }
+
+            if let Some(name) = href
+                .path_segments()
+                .and_then(|s| s.rev().find(|s| !s.is_empty()))
+            {
+                names.push(
+                    percent_encoding::percent_decode_str(name)
+                        .decode_utf8_lossy()
+                        .to_string(),
+                );
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Get the href of every response and if it is a collection from a PROPFIND result
+    fn parse_multistatus(xml: &str) -> Result<Vec<(String, bool)>> {
+        use quick_xml::events::Event;
+
+        let mut reader = quick_xml::Reader::from_str(xml);
+        reader.trim_text(true);
+
+        let mut buf = vec![];
+        let mut element = vec![];
+        let mut href = String::new();
+        let mut collection = false;
+        let mut responses = vec![];
+
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(e) => {
+                    match e.local_name() {
+                        b"response" => {
+                            href.clear();
+                            collection = false;
+                        }
+                        b"collection" => collection = true,
+                        _ => {}
+                    }
+                    element = e.local_name().to_vec();
+                }
+                Event::Empty(e) if e.local_name() == b"collection" => collection = true,
+                Event::Text(t) if element == b"href" => href = t.unescape_and_decode(&reader)?,
+                Event::End(e) => {
+                    if e.local_name() == b"response" {
+                        responses.push((href.clone(), collection));
+                    }
+                    element.clear();
+                }
+                Event::Eof => return Ok(responses),
+                _ => {}
+            }
+            buf.clear();
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Adapter for WebDav {
+    async fn containers(&self) -> Result<Vec<String>> {
+        self.list(&[], true).await
+    }
+
+    async fn create_container(&self, container: &str) -> Result<()> {
+        let res = self.request("MKCOL", &[container])?.send().await?;
+
+        // the collection already exists - so no error
+        if res.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(());
+        }
+
+        util::http_status(res)?;
+        Ok(())
+    }
+
+    async fn remove_container(&self, container: &str) -> Result<()> {
+        let container = util::streamline(container);
+
+        let res = self.request("DELETE", &[&container])?.send().await?;
+        util::http_status(res)?;
+        Ok(())
+    }
+
+    async fn items(&self, container: &str) -> Result<Vec<String>> {
+        let container = util::streamline(container);
+
+        self.list(&[&container], false).await
+    }
+
+    async fn create_item(
+        &self,
+        container: &str,
+        item: &str,
+        reader: impl tokio::io::AsyncRead + Unpin + Send + Sync + 'static,
+    ) -> Result<()> {
+        let item = util::streamline_item(item)?;
+
+        let body = reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(reader));
+        let res = self
+            .request("PUT", &[container, &item])?
+            .body(body)
+            .send()
+            .await?;
+        util::http_status(res)?;
+        Ok(())
+    }
+
+    async fn read_item(
+        &self,
+        container: &str,
+        item: &str,
+    ) -> Result<Box<dyn tokio::io::AsyncRead + Unpin + Send + Sync>> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let res = self.request("GET", &[&container, &item])?.send().await?;
+        Ok(util::response_reader(util::http_status(res)?))
+    }
+
+    async fn remove_item(&self, container: &str, item: &str) -> Result<()> {
+        let container = util::streamline(container);
+        let item = util::streamline_item(item)?;
+
+        let res = self.request("DELETE", &[&container, &item])?.send().await?;
+        util::http_status(res)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+#![cfg(feature = "archive")]
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_archive() -> stow::Result<()> {
+    tokio::fs::create_dir_all("./data").await?;
+
+    for path in ["./data/test.tar", "./data/test.tar.gz", "./data/test.zip"] {
+        let _ = tokio::fs::remove_file(path).await;
+
+        // a missing archive can't be opened read-only
+        assert!(
+            stow::Location::new_archive(path, stow::ArchiveMode::ReadOnly)
+                .await
+                .is_err()
+        );
+
+        let archive = stow::Location::new_archive(path, stow::ArchiveMode::Rewrite).await?;
+
+        let container_1 = "container-1";
+        let container_2 = "container-2";
+
+        // create new containers if not avilable
+        archive.create_container(container_1).await?;
+        archive.create_container(container_2).await?;
+        archive.create_container(container_2).await?;
+        assert_eq!(
+            archive.containers().await?,
+            vec![container_1.to_string(), container_2.to_string()]
+        );
+
+        // create two test.txt file
+        archive
+            .create_item(container_1, "test.txt", reader("Hello World 1").await?)
+            .await?;
+        archive
+            .create_item(container_2, "test.txt", reader("Hello World 2").await?)
+            .await?;
+        assert!(archive
+            .items(container_2)
+            .await?
+            .contains(&String::from("test.txt")));
+
+        // rewrite the test.txt file
+        archive
+            .create_item(container_1, "test.txt", reader("Hello World 1 New").await?)
+            .await?;
+        assert_eq!(archive.items(container_1).await?.len(), 1);
+        assert_eq!(
+            read(&archive, container_1, "test.txt").await?,
+            "Hello World 1 New"
+        );
+
+        // reopen the archive read-only
+        let read_only = stow::Location::new_archive(path, stow::ArchiveMode::ReadOnly).await?;
+        assert_eq!(
+            read(&read_only, container_2, "test.txt").await?,
+            "Hello World 2"
+        );
+        assert!(read_only
+            .remove_item(container_2, "test.txt")
+            .await
+            .is_err());
+
+        // remove the item.txt in container 2
+        archive.remove_item(container_2, "test.txt").await?;
+        assert!(archive.read_item(container_2, "test.txt").await.is_err());
+
+        // remove the container
+        archive.remove_container(container_2).await?;
+        archive.remove_container(container_1).await?;
+        assert!(archive.containers().await?.is_empty());
+
+        tokio::fs::remove_file(path).await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_archive_append() -> stow::Result<()> {
+    tokio::fs::create_dir_all("./data").await?;
+
+    for path in [
+        "./data/append.tar",
+        "./data/append.tar.gz",
+        "./data/append.zip",
+    ] {
+        let _ = tokio::fs::remove_file(path).await;
+        let archive = stow::Location::new_archive(path, stow::ArchiveMode::Append).await?;
+
+        let container = "container";
+        archive.create_container(container).await?;
+        archive
+            .create_item(container, "test.txt", reader("Hello World").await?)
+            .await?;
+
+        // the appended entry shadows the old one
+        archive
+            .create_item(container, "test.txt", reader("Hello World New").await?)
+            .await?;
+        assert_eq!(
+            archive.items(container).await?,
+            vec!["test.txt".to_string()]
+        );
+        assert_eq!(
+            read(&archive, container, "test.txt").await?,
+            "Hello World New"
+        );
+
+        // removing is not possible without a rewrite
+        assert!(archive.remove_item(container, "test.txt").await.is_err());
+
+        tokio::fs::remove_file(path).await?;
+    }
+
+    Ok(())
+}
+
+async fn read(location: &stow::Location, container: &str, item: &str) -> stow::Result<String> {
+    let mut buf = String::new();
+    location
+        .read_item(container, item)
+        .await?
+        .read_to_string(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len());
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}
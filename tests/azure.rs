@@ -0,0 +1,95 @@
+#![cfg(feature = "azure")]
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_azure() -> stow::Result<()> {
+    if let Err(e) = dotenv::dotenv() {
+        if !e.not_found() {
+            let e: Result<(), dotenv::Error> = Err(e);
+            e.unwrap();
+        }
+    }
+
+    // skipped without an Azure account, e.g. in CI
+    if std::env::var("STOW_AZURE_ACCOUNT").is_err() {
+        eprintln!("STOW_AZURE_ACCOUNT is not set, skipping test_azure");
+        return Ok(());
+    }
+
+    let account = std::env::var("STOW_AZURE_ACCOUNT")?;
+    let access_key = std::env::var("STOW_AZURE_ACCESS_KEY")?;
+    let container_1 = std::env::var("STOW_TEST_CONTAINER_1")?;
+    let container_2 = std::env::var("STOW_TEST_CONTAINER_2")?;
+
+    // create a new environment if not avilable
+    // the endpoint can be set to test against the azurite emulator
+    let azure = match std::env::var("STOW_AZURE_ENDPOINT") {
+        Ok(endpoint) => stow::Location::Azure(
+            stow::Azure::new(&account, stow::AzureAuth::SharedKey(access_key))
+                .await?
+                .with_endpoint(&endpoint),
+        ),
+        Err(_) => stow::Location::new_azure(&account, &access_key).await?,
+    };
+
+    // create new containers if not avilable
+    azure.create_container(&container_1).await?;
+    azure.create_container(&container_2).await?;
+
+    assert!(azure
+        .containers()
+        .await?
+        .contains(&String::from(&container_1)));
+    assert!(azure
+        .containers()
+        .await?
+        .contains(&String::from(&container_2)));
+
+    // create two test.txt file
+    azure
+        .create_item(&container_1, "test.txt", reader("Hello World 1").await?)
+        .await?;
+    azure
+        .create_item(&container_2, "test.txt", reader("Hello World 2").await?)
+        .await?;
+
+    assert!(azure
+        .items(&container_2)
+        .await?
+        .contains(&String::from("test.txt")));
+
+    // rewrite the test.txt file
+    azure
+        .create_item(&container_1, "test.txt", reader("Hello World 1 New").await?)
+        .await?;
+
+    // read the test.txt file
+    let mut buf = vec![];
+    azure
+        .read_item(&container_1, "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World 1 New"[0..], &buf);
+
+    // remove the item.txt in container 2
+    azure.remove_item(&container_2, "test.txt").await?;
+    assert!(azure.read_item(&container_2, "test.txt").await.is_err());
+    // remove the container 2
+    azure.remove_container(&container_2).await?;
+
+    // remove the item.txt in container 1
+    azure.remove_item(&container_1, "test.txt").await?;
+    assert!(azure.read_item(&container_1, "test.txt").await.is_err());
+    azure.remove_container(&container_1).await?;
+
+    Ok(())
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len());
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}
@@ -0,0 +1,428 @@
+#![cfg(feature = "b2")]
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_b2() -> stow::Result<()> {
+    if let Err(e) = dotenv::dotenv() {
+        if !e.not_found() {
+            let e: Result<(), dotenv::Error> = Err(e);
+            e.unwrap();
+        }
+    }
+
+    // skipped without a B2 account, e.g. in CI
+    if std::env::var("STOW_B2_KEY_ID").is_err() {
+        eprintln!("STOW_B2_KEY_ID is not set, skipping test_b2");
+        return Ok(());
+    }
+
+    let key_id = std::env::var("STOW_B2_KEY_ID")?;
+    let application_key = std::env::var("STOW_B2_APPLICATION_KEY")?;
+    let container_1 = std::env::var("STOW_TEST_CONTAINER_1")?;
+    let container_2 = std::env::var("STOW_TEST_CONTAINER_2")?;
+
+    // create a new environment if not avilable
+    // the api url can be set to test against a mock server
+    let b2 = match std::env::var("STOW_B2_API_URL") {
+        Ok(url) => stow::Location::B2(
+            stow::B2::new_with_url(&url, &key_id, &application_key)
+                .await?
+                .with_part_size(4),
+        ),
+        Err(_) => stow::Location::new_b2(&key_id, &application_key).await?,
+    };
+
+    // create new containers if not avilable
+    b2.create_container(&container_1).await?;
+    b2.create_container(&container_2).await?;
+
+    assert!(b2.containers().await?.contains(&String::from(&container_1)));
+    assert!(b2.containers().await?.contains(&String::from(&container_2)));
+
+    // create two test.txt file
+    b2.create_item(&container_1, "test.txt", reader("Hello World 1").await?)
+        .await?;
+    b2.create_item(&container_2, "test.txt", reader("Hello World 2").await?)
+        .await?;
+
+    assert!(b2
+        .items(&container_2)
+        .await?
+        .contains(&String::from("test.txt")));
+
+    // rewrite the test.txt file
+    b2.create_item(&container_1, "test.txt", reader("Hello World 1 New").await?)
+        .await?;
+
+    // read the test.txt file
+    let mut buf = vec![];
+    b2.read_item(&container_1, "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World 1 New"[0..], &buf);
+
+    // remove the item.txt in container 2
+    b2.remove_item(&container_2, "test.txt").await?;
+    assert!(b2.read_item(&container_2, "test.txt").await.is_err());
+    // remove the container 2
+    b2.remove_container(&container_2).await?;
+
+    // remove the item.txt in container 1
+    b2.remove_item(&container_1, "test.txt").await?;
+    assert!(b2.read_item(&container_1, "test.txt").await.is_err());
+    b2.remove_container(&container_1).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_b2_mock() -> stow::Result<()> {
+    use stow::Adapter;
+
+    let (url, state) = mock_server().await?;
+    let b2 = stow::B2::new_with_url(&url, "key-id", "application-key")
+        .await?
+        .with_part_size(5);
+
+    b2.create_container("container-1").await?;
+    b2.create_container("container-2").await?;
+    // creating an available bucket again is fine
+    b2.create_container("container-1").await?;
+    assert_eq!(b2.containers().await?, vec!["container-1", "container-2"]);
+
+    // small items are uploaded at once, larger ones in parts of 5 bytes
+    b2.create_item("container-1", "small.txt", reader("Hello").await?)
+        .await?;
+    b2.create_item("container-1", "large.txt", reader("Hello World 1").await?)
+        .await?;
+    assert_eq!(read(&b2, "container-1", "small.txt").await?, b"Hello");
+    assert_eq!(
+        read(&b2, "container-1", "large.txt").await?,
+        b"Hello World 1"
+    );
+    assert!(state.lock().unwrap().large_files.is_empty());
+
+    b2.create_item(
+        "container-1",
+        "large.txt",
+        reader("Hello World 1 New").await?,
+    )
+    .await?;
+    assert_eq!(
+        read(&b2, "container-1", "large.txt").await?,
+        b"Hello World 1 New"
+    );
+    assert_eq!(
+        b2.items("container-1").await?,
+        vec!["large.txt", "small.txt"]
+    );
+
+    // an expired authorization is renewed once
+    state.lock().unwrap().expired = true;
+    assert_eq!(b2.items("container-2").await?, Vec::<String>::new());
+    assert_eq!(state.lock().unwrap().authorizations, 2);
+
+    b2.remove_item("container-1", "small.txt").await?;
+    assert!(b2.read_item("container-1", "small.txt").await.is_err());
+    assert!(b2.remove_item("container-1", "small.txt").await.is_err());
+
+    // buckets are emptied before they are removed
+    b2.remove_container("container-1").await?;
+    b2.remove_container("container-2").await?;
+    assert!(b2.containers().await?.is_empty());
+
+    Ok(())
+}
+
+/// Buckets and files of the mock server
+#[derive(Default)]
+struct State {
+    buckets: BTreeMap<String, BTreeMap<String, Vec<u8>>>,
+    /// Bucket and name of the unfinished large files, with their uploaded parts
+    large_files: HashMap<String, (String, String, Vec<Vec<u8>>)>,
+    authorizations: usize,
+    expired: bool,
+}
+
+/// Serve the endpoints of the B2 native API the adapter uses on a local port
+async fn mock_server() -> stow::Result<(String, Arc<Mutex<State>>)> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let state = Arc::new(Mutex::new(State::default()));
+
+    let (u, s) = (url.clone(), state.clone());
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let (status, body) = match read_request(&mut socket).await {
+                Some(request) => handle(&mut s.lock().unwrap(), &u, request),
+                None => continue,
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+            let _ = socket.write_all(&body).await;
+        }
+    });
+
+    Ok((url, state))
+}
+
+/// Method, path, lowercase headers and body of a request
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+async fn read_request(socket: &mut tokio::net::TcpStream) -> Option<Request> {
+    let mut buf = vec![];
+    let end = loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+        let mut chunk = [0; 4096];
+        match socket.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(n, v)| (n.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(0);
+    let mut body = buf.split_off(end + 4);
+    while body.len() < length {
+        let mut chunk = [0; 4096];
+        match socket.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+        }
+    }
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+fn sha1(data: &[u8]) -> String {
+    use sha1::Digest;
+    format!("{:x}", sha1::Sha1::digest(data))
+}
+
+fn json(value: serde_json::Value) -> (&'static str, Vec<u8>) {
+    ("200 OK", value.to_string().into_bytes())
+}
+
+fn error(status: &'static str) -> (&'static str, Vec<u8>) {
+    (status, br#"{"code": "error"}"#.to_vec())
+}
+
+/// Answer the request like the B2 native API
+fn handle(state: &mut State, url: &str, request: Request) -> (&'static str, Vec<u8>) {
+    let header = |name: &str| request.headers.get(name).cloned().unwrap_or_default();
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap_or_default();
+    let field = |name: &str| body[name].as_str().unwrap_or_default().to_string();
+    let token = format!("token-{}", state.authorizations);
+
+    let path = request.path.clone();
+    let (operation, rest) = match path.trim_start_matches('/').split_once('/') {
+        Some((operation, rest)) => (operation, rest),
+        None => return error("404 Not Found"),
+    };
+
+    // authorization of the account and of the uploads
+    if rest == "v2/b2_authorize_account" {
+        if !header("authorization").starts_with("Basic ") {
+            return error("401 Unauthorized");
+        }
+        state.authorizations += 1;
+        state.expired = false;
+        return json(serde_json::json!({
+            "accountId": "account",
+            "authorizationToken": format!("token-{}", state.authorizations),
+            "apiUrl": url,
+            "downloadUrl": url,
+            "recommendedPartSize": 100,
+            "absoluteMinimumPartSize": 5,
+        }));
+    }
+    let authorized = match operation {
+        "upload" | "upload_part" => header("authorization") == "upload-token",
+        _ => header("authorization") == token && !state.expired,
+    };
+    if !authorized {
+        return error("401 Unauthorized");
+    }
+
+    match (request.method.as_str(), operation) {
+        ("POST", "b2api") => {}
+        ("POST", "upload") => {
+            if header("x-bz-content-sha1") != sha1(&request.body) {
+                return error("400 Bad Request");
+            }
+            let name = percent_encoding::percent_decode_str(&header("x-bz-file-name"))
+                .decode_utf8_lossy()
+                .to_string();
+            let bucket = rest.trim_start_matches("id-");
+            return match state.buckets.get_mut(bucket) {
+                Some(files) => {
+                    files.insert(name.clone(), request.body);
+                    json(serde_json::json!({ "fileName": name }))
+                }
+                None => error("400 Bad Request"),
+            };
+        }
+        ("POST", "upload_part") => {
+            let (_, _, parts) = match state.large_files.get_mut(rest) {
+                Some(file) => file,
+                None => return error("400 Bad Request"),
+            };
+            if header("x-bz-content-sha1") != sha1(&request.body)
+                || header("x-bz-part-number") != (parts.len() + 1).to_string()
+            {
+                return error("400 Bad Request");
+            }
+            parts.push(request.body);
+            return json(serde_json::json!({}));
+        }
+        ("GET", "file") => {
+            let (bucket, name) = rest.split_once('/').unwrap_or_default();
+            let name = percent_encoding::percent_decode_str(name).decode_utf8_lossy();
+            return match state.buckets.get(bucket).and_then(|f| f.get(&*name)) {
+                Some(data) => ("200 OK", data.clone()),
+                None => error("404 Not Found"),
+            };
+        }
+        _ => return error("404 Not Found"),
+    }
+
+    let bucket = field("bucketId").trim_start_matches("id-").to_string();
+    match rest.trim_start_matches("v2/") {
+        "b2_list_buckets" => json(serde_json::json!({
+            "buckets": state
+                .buckets
+                .keys()
+                .filter(|b| body["bucketName"].is_null() || body["bucketName"] == **b)
+                .map(|b| serde_json::json!({ "bucketName": b, "bucketId": format!("id-{}", b) }))
+                .collect::<Vec<_>>(),
+        })),
+        "b2_create_bucket" => {
+            let bucket = field("bucketName");
+            if state.buckets.contains_key(&bucket) {
+                return error("400 Bad Request");
+            }
+            state.buckets.insert(bucket.clone(), BTreeMap::new());
+            json(serde_json::json!({ "bucketName": bucket, "bucketId": format!("id-{}", bucket) }))
+        }
+        "b2_delete_bucket" => match state.buckets.get(&bucket) {
+            Some(files) if files.is_empty() => {
+                state.buckets.remove(&bucket);
+                json(serde_json::json!({}))
+            }
+            _ => error("400 Bad Request"),
+        },
+        "b2_list_file_names" | "b2_list_file_versions" => {
+            let files = match state.buckets.get(&bucket) {
+                Some(files) => files,
+                None => return error("400 Bad Request"),
+            };
+            let start = field("startFileName");
+            let files: Vec<_> = files
+                .keys()
+                .filter(|n| **n >= start)
+                .map(
+                    |n| serde_json::json!({ "fileName": n, "fileId": format!("{}/{}", bucket, n) }),
+                )
+                .collect();
+            json(serde_json::json!({
+                "files": files,
+                "nextFileName": null,
+            }))
+        }
+        "b2_delete_file_version" => {
+            let id = field("fileId");
+            let (bucket, name) = id.split_once('/').unwrap_or_default();
+            match state.buckets.get_mut(bucket).and_then(|f| f.remove(name)) {
+                Some(_) if name == field("fileName") => json(serde_json::json!({})),
+                _ => error("400 Bad Request"),
+            }
+        }
+        "b2_get_upload_url" => json(serde_json::json!({
+            "uploadUrl": format!("{}/upload/id-{}", url, bucket),
+            "authorizationToken": "upload-token",
+        })),
+        "b2_start_large_file" => {
+            let id = format!("large-{}", state.large_files.len());
+            state
+                .large_files
+                .insert(id.clone(), (bucket, field("fileName"), vec![]));
+            json(serde_json::json!({ "fileId": id }))
+        }
+        "b2_get_upload_part_url" => json(serde_json::json!({
+            "uploadUrl": format!("{}/upload_part/{}", url, field("fileId")),
+            "authorizationToken": "upload-token",
+        })),
+        "b2_finish_large_file" => {
+            let (bucket, name, parts) = match state.large_files.remove(&field("fileId")) {
+                Some(file) => file,
+                None => return error("400 Bad Request"),
+            };
+            let hashes: Vec<String> = parts.iter().map(|p| sha1(p)).collect();
+            if body["partSha1Array"] != serde_json::json!(hashes) {
+                return error("400 Bad Request");
+            }
+            match state.buckets.get_mut(&bucket) {
+                Some(files) => {
+                    files.insert(name.clone(), parts.concat());
+                    json(serde_json::json!({ "fileName": name }))
+                }
+                None => error("400 Bad Request"),
+            }
+        }
+        "b2_cancel_large_file" => {
+            state.large_files.remove(&field("fileId"));
+            json(serde_json::json!({}))
+        }
+        _ => error("404 Not Found"),
+    }
+}
+
+async fn read(b2: &stow::B2, container: &str, item: &str) -> stow::Result<Vec<u8>> {
+    use stow::Adapter;
+
+    let mut buf = vec![];
+    b2.read_item(container, item)
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len());
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}
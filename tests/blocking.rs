@@ -0,0 +1,52 @@
+#![cfg(feature = "blocking")]
+
+use std::io::{Read, Write};
+
+#[test]
+fn test_blocking() -> stow::Result<()> {
+    // create a new empty in-memory location, without an async runtime
+    let memory = stow::blocking::Location::new(stow::Location::new_memory())?;
+
+    let container = "container-1";
+    memory.create_container(container)?;
+    assert!(memory.containers()?.contains(&String::from(container)));
+
+    // create an item from a reader
+    memory.create_item(container, "test.txt", &b"Hello World"[..])?;
+    assert!(memory.items(container)?.contains(&String::from("test.txt")));
+
+    let mut buf = vec![];
+    memory
+        .read_item(container, "test.txt")?
+        .read_to_end(&mut buf)?;
+    assert_eq!(&b"Hello World"[..], &buf);
+
+    // create a bigger item with a writer
+    let data = (0..300_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let mut writer = memory.item_writer(container, "big.bin")?;
+    for part in data.chunks(10_000) {
+        writer.write_all(part)?;
+    }
+    writer.finish()?;
+
+    let mut buf = vec![];
+    memory
+        .read_item(container, "big.bin")?
+        .read_to_end(&mut buf)?;
+    assert_eq!(data, buf);
+
+    // dropping the writer without finishing it doesn't create the item
+    let mut writer = memory.item_writer(container, "dropped.txt")?;
+    writer.write_all(b"Hello")?;
+    drop(writer);
+    assert!(memory.read_item(container, "dropped.txt").is_err());
+
+    // remove the items and the container
+    memory.remove_item(container, "test.txt")?;
+    memory.remove_item(container, "big.bin")?;
+    assert!(memory.read_item(container, "test.txt").is_err());
+    memory.remove_container(container)?;
+    assert!(memory.containers()?.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,239 @@
+#![cfg(feature = "cache")]
+
+use std::time::Duration;
+use stow::Adapter;
+use tokio::io::AsyncReadExt;
+
+async fn read(location: &impl Adapter, container: &str, item: &str) -> stow::Result<Vec<u8>> {
+    let mut buf = vec![];
+    location
+        .read_item(container, item)
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+async fn write(location: &stow::Location, container: &str, item: &str, data: &'static [u8]) {
+    location
+        .create_item(container, item, std::io::Cursor::new(data))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_cache() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+    let copies = stow::Location::new_memory().await?;
+    let cache = stow::Cache::new(memory.clone(), copies.clone())
+        .await?
+        .with_max_size(25);
+
+    let container = "container-1";
+    cache.create_container(container).await?;
+    write(&memory, container, "a.txt", b"aaaaaaaaa1").await;
+    write(&memory, container, "b.txt", b"bbbbbbbbb1").await;
+    write(&memory, container, "c.txt", b"ccccccccc1").await;
+
+    // read items are kept, changes of the wrapped location are not noticed
+    assert_eq!(read(&cache, container, "a.txt").await?, b"aaaaaaaaa1");
+    write(&memory, container, "a.txt", b"aaaaaaaaa2").await;
+    assert_eq!(read(&cache, container, "a.txt").await?, b"aaaaaaaaa1");
+    assert_eq!(copies.items(stow::CACHE_CONTAINER).await?.len(), 1);
+
+    // the least recently used copy is evicted, when the size limit is exceeded
+    assert_eq!(read(&cache, container, "b.txt").await?, b"bbbbbbbbb1");
+    assert_eq!(read(&cache, container, "a.txt").await?, b"aaaaaaaaa1");
+    assert_eq!(read(&cache, container, "c.txt").await?, b"ccccccccc1");
+    assert_eq!(copies.items(stow::CACHE_CONTAINER).await?.len(), 2);
+
+    write(&memory, container, "b.txt", b"bbbbbbbbb2").await;
+    assert_eq!(read(&cache, container, "b.txt").await?, b"bbbbbbbbb2");
+
+    // creating and removing items through the cache invalidates their copies
+    cache
+        .create_item(container, "c.txt", std::io::Cursor::new(b"ccccccccc3"))
+        .await?;
+    assert_eq!(read(&cache, container, "c.txt").await?, b"ccccccccc3");
+
+    cache.remove_item(container, "c.txt").await?;
+    assert!(cache.read_item(container, "c.txt").await.is_err());
+
+    cache.remove_item(container, "a.txt").await?;
+    cache.remove_item(container, "b.txt").await?;
+    cache.remove_container(container).await?;
+    assert!(copies.items(stow::CACHE_CONTAINER).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_names() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+    let copies = stow::Location::new_memory().await?;
+    let cache = stow::Cache::new(memory.clone(), copies.clone()).await?;
+
+    let container = "container-1";
+    cache.create_container(container).await?;
+    write(&memory, container, "test.txt", b"Hello World 1").await;
+
+    // differently written names of the same item share the copy
+    assert_eq!(read(&cache, container, "test.txt").await?, b"Hello World 1");
+    assert_eq!(
+        read(&cache, "Container-1", "Test.TXT").await?,
+        b"Hello World 1"
+    );
+    assert_eq!(copies.items(stow::CACHE_CONTAINER).await?.len(), 1);
+
+    // and overwriting or removing it through another name invalidates the copy
+    cache
+        .create_item(
+            container,
+            "Test.TXT",
+            std::io::Cursor::new(b"Hello World 2"),
+        )
+        .await?;
+    assert_eq!(read(&cache, container, "test.txt").await?, b"Hello World 2");
+
+    cache.remove_item(container, "TEST.txt").await?;
+    assert!(cache.read_item(container, "test.txt").await.is_err());
+    assert!(copies.items(stow::CACHE_CONTAINER).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_ttl() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+    let cache = stow::Cache::new(memory.clone(), stow::Location::new_memory().await?)
+        .await?
+        .with_ttl(Duration::from_millis(50));
+
+    let container = "container-1";
+    memory.create_container(container).await?;
+    write(&memory, container, "test.txt", b"Hello World 1").await;
+    assert_eq!(read(&cache, container, "test.txt").await?, b"Hello World 1");
+
+    // without an ETag, the item is fetched again after the TTL
+    write(&memory, container, "test.txt", b"Hello World 2").await;
+    assert_eq!(read(&cache, container, "test.txt").await?, b"Hello World 1");
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(read(&cache, container, "test.txt").await?, b"Hello World 2");
+
+    Ok(())
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_cache_etag() -> stow::Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::AsyncWriteExt;
+
+    // serve an item, whose version is part of its data and ETag, and count the downloads
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/", listener.local_addr()?);
+    let version = Arc::new(AtomicUsize::new(1));
+    let downloads = Arc::new(AtomicUsize::new(0));
+
+    let (v, d) = (version.clone(), downloads.clone());
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0; 4096];
+            let n = socket.read(&mut buf).await.unwrap_or_default();
+
+            let version = v.load(Ordering::SeqCst);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: 13\r\netag: \"{}\"\r\nconnection: close\r\n\r\n",
+                version
+            );
+            let response = if buf[..n].starts_with(b"HEAD") {
+                head
+            } else {
+                d.fetch_add(1, Ordering::SeqCst);
+                format!("{}Hello World {}", head, version)
+            };
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    // every read validates the copy
+    let cache = stow::Cache::new(
+        stow::Location::new_http(&url).await?,
+        stow::Location::new_memory().await?,
+    )
+    .await?
+    .with_ttl(Duration::from_secs(0));
+
+    assert_eq!(
+        read(&cache, "container", "test.txt").await?,
+        b"Hello World 1"
+    );
+    assert_eq!(
+        read(&cache, "container", "test.txt").await?,
+        b"Hello World 1"
+    );
+    assert_eq!(downloads.load(Ordering::SeqCst), 1);
+
+    // a changed ETag fetches the item again
+    version.store(2, Ordering::SeqCst);
+    assert_eq!(
+        read(&cache, "container", "test.txt").await?,
+        b"Hello World 2"
+    );
+    assert_eq!(downloads.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_concurrent_write() -> stow::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let memory = stow::Location::new_memory().await?;
+    let cache = stow::Cache::new(memory.clone(), stow::Location::new_memory().await?).await?;
+
+    let container = "container-1";
+    cache.create_container(container).await?;
+    write(&memory, container, "test.txt", b"Hello World 1").await;
+
+    // start overwriting the item, the wrapped location keeps the old data until the upload ends
+    let (mut writer, reader) = tokio::io::duplex(64);
+    let upload = {
+        let cache = cache.clone();
+        tokio::spawn(async move { cache.create_item(container, "test.txt", reader).await })
+    };
+    writer.write_all(b"Hello World 2").await?;
+    tokio::task::yield_now().await;
+
+    // a read in the meantime gets the old data, but doesn't keep it after the upload
+    assert_eq!(read(&cache, container, "test.txt").await?, b"Hello World 1");
+    drop(writer);
+    upload.await.unwrap()?;
+    assert_eq!(read(&cache, container, "test.txt").await?, b"Hello World 2");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_open_copy() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+    let cache = stow::Cache::new(memory.clone(), stow::Location::new_local("./data").await?)
+        .await?
+        .with_ttl(Duration::from_secs(0));
+
+    let container = "container-1";
+    memory.create_container(container).await?;
+    write(&memory, container, "test.txt", b"Hello World 1").await;
+
+    // fetching the item again doesn't overwrite the copy, which is still read
+    let mut first = cache.read_item(container, "test.txt").await?;
+    write(&memory, container, "test.txt", b"Hello World 2").await;
+    assert_eq!(read(&cache, container, "test.txt").await?, b"Hello World 2");
+
+    let mut buf = vec![];
+    first.read_to_end(&mut buf).await?;
+    assert_eq!(buf, b"Hello World 1");
+
+    Ok(())
+}
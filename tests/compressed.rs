@@ -0,0 +1,59 @@
+#![cfg(feature = "compression")]
+
+use tokio::io::AsyncReadExt;
+
+async fn read(location: &stow::Location, container: &str, item: &str) -> stow::Result<Vec<u8>> {
+    let mut buf = vec![];
+    location
+        .read_item(container, item)
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+async fn write(location: &stow::Location, container: &str, item: &str, data: Vec<u8>) {
+    location
+        .create_item(container, item, std::io::Cursor::new(data))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_compressed() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+    let data = b"2021-05-01 12:00:00 INFO request handled\n".repeat(1000);
+
+    let container = "container-1";
+    memory.create_container(container).await?;
+
+    for codec in [stow::Codec::Gzip, stow::Codec::Zstd].iter() {
+        let compressed = stow::Compressed::new(memory.clone(), *codec);
+        let location = stow::Location::new_compressed(memory.clone(), *codec).await?;
+
+        // the stored item is smaller and records its codec
+        write(&location, container, "test.log", data.clone()).await;
+        assert!(read(&memory, container, "test.log").await?.len() < data.len() / 10);
+        assert_eq!(read(&location, container, "test.log").await?, data);
+        assert_eq!(compressed.codec(container, "test.log").await?, Some(*codec));
+
+        write(&location, container, "empty.log", vec![]).await;
+        assert_eq!(read(&location, container, "empty.log").await?, b"");
+    }
+
+    // items are read with the codec they were created with
+    let gzip = stow::Location::new_compressed(memory.clone(), stow::Codec::Gzip).await?;
+    let zstd = stow::Location::new_compressed(memory.clone(), stow::Codec::Zstd).await?;
+    write(&gzip, container, "gzip.log", data.clone()).await;
+    assert_eq!(read(&zstd, container, "gzip.log").await?, data);
+
+    // uncompressed items are read as they are
+    let compressed = stow::Compressed::new(memory.clone(), stow::Codec::Zstd);
+    for legacy in [&b""[..], b"Hello", b"Hello World", &data].iter() {
+        write(&memory, container, "legacy.log", legacy.to_vec()).await;
+        assert_eq!(read(&zstd, container, "legacy.log").await?, *legacy);
+        assert_eq!(compressed.codec(container, "legacy.log").await?, None);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+#![cfg(feature = "dedup")]
+
+use stow::Adapter;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_dedup() -> stow::Result<()> {
+    let dedup = stow::Location::new_dedup(stow::Location::new_memory().await?).await?;
+
+    let container_1 = "container-1";
+    let container_2 = "container-2";
+
+    // create new containers if not avilable
+    dedup.create_container(container_1).await?;
+    dedup.create_container(container_2).await?;
+    assert_eq!(
+        dedup.containers().await?,
+        vec![container_1.to_string(), container_2.to_string()]
+    );
+
+    // create two test.txt file
+    dedup
+        .create_item(container_1, "test.txt", reader("Hello World 1").await?)
+        .await?;
+    dedup
+        .create_item(container_2, "test.txt", reader("Hello World 2").await?)
+        .await?;
+    assert!(dedup
+        .items(container_2)
+        .await?
+        .contains(&String::from("test.txt")));
+
+    // rewrite the test.txt file
+    dedup
+        .create_item(container_1, "test.txt", reader("Hello World 1 New").await?)
+        .await?;
+    let mut buf = vec![];
+    dedup
+        .read_item(container_1, "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World 1 New"[0..], &buf);
+
+    // remove the item.txt in container 2
+    dedup.remove_item(container_2, "test.txt").await?;
+    assert!(dedup.read_item(container_2, "test.txt").await.is_err());
+
+    // remove the container
+    dedup.remove_container(container_2).await?;
+    dedup.remove_container(container_1).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dedup_chunks() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+
+    for chunking in [
+        stow::Chunking::Fixed(16),
+        stow::Chunking::ContentDefined {
+            min: 8,
+            avg: 16,
+            max: 64,
+        },
+    ] {
+        let dedup = stow::Dedup::new(memory.clone())
+            .await?
+            .with_chunking(chunking);
+
+        let data = "Hello World, this item is stored in chunks. ".repeat(20);
+        dedup.create_container("container-1").await?;
+        dedup.create_container("container-2").await?;
+        dedup
+            .create_item("container-1", "test.txt", reader(&data).await?)
+            .await?;
+        let chunks = memory.items(stow::DEDUP_CONTAINER).await?.len();
+        assert!(chunks > 1);
+
+        // the same data in another container doesn't need new chunks
+        dedup
+            .create_item("container-2", "copy.txt", reader(&data).await?)
+            .await?;
+        assert_eq!(memory.items(stow::DEDUP_CONTAINER).await?.len(), chunks);
+        assert_eq!(read(&dedup, "container-2", "copy.txt").await?, data);
+
+        // the chunks are removed, when no item uses them anymore
+        dedup.remove_item("container-1", "test.txt").await?;
+        assert_eq!(dedup.collect_garbage().await?, 0);
+        dedup.remove_container("container-2").await?;
+        assert_eq!(dedup.collect_garbage().await?, chunks);
+
+        // and stored again, when the data is written again
+        dedup
+            .create_item("container-1", "test.txt", reader(&data).await?)
+            .await?;
+        assert_eq!(memory.items(stow::DEDUP_CONTAINER).await?.len(), chunks);
+        assert_eq!(read(&dedup, "container-1", "test.txt").await?, data);
+        dedup.remove_item("container-1", "test.txt").await?;
+        assert_eq!(dedup.collect_garbage().await?, chunks);
+
+        dedup.remove_container("container-1").await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dedup_chunk_container() -> stow::Result<()> {
+    let dedup = stow::Dedup::new(stow::Location::new_memory().await?).await?;
+    let invalid = |res: stow::Result<()>| matches!(res, Err(stow::StowError::InvalidNameError(_)));
+
+    // the container of the chunks is hidden and can't be used, regardless of how its name is written
+    assert!(dedup.containers().await?.is_empty());
+    for container in [stow::DEDUP_CONTAINER, "Dedup-Chunks", " dedup-chunks!"] {
+        assert!(invalid(dedup.create_container(container).await));
+        assert!(invalid(dedup.remove_container(container).await));
+        assert!(invalid(dedup.items(container).await.map(|_| ())));
+        assert!(invalid(
+            dedup
+                .create_item(container, "test.chunk", reader("Hello World").await?)
+                .await
+        ));
+        assert!(invalid(
+            dedup.read_item(container, "test.chunk").await.map(|_| ())
+        ));
+        assert!(invalid(dedup.remove_item(container, "test.chunk").await));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dedup_corrupted_chunk() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+    let dedup = stow::Dedup::new(memory.clone())
+        .await?
+        .with_chunking(stow::Chunking::Fixed(16));
+
+    let data = "Hello World, this item is stored in chunks. ".repeat(4);
+    dedup.create_container("container-1").await?;
+    dedup
+        .create_item("container-1", "test.txt", reader(&data).await?)
+        .await?;
+    assert_eq!(read(&dedup, "container-1", "test.txt").await?, data);
+
+    // a changed chunk is noticed, when the item is read
+    let chunk = memory.items(stow::DEDUP_CONTAINER).await?.remove(1);
+    memory
+        .create_item(stow::DEDUP_CONTAINER, &chunk, reader("Hello Mars").await?)
+        .await?;
+    assert!(read(&dedup, "container-1", "test.txt").await.is_err());
+
+    Ok(())
+}
+
+async fn read(location: &impl Adapter, container: &str, item: &str) -> stow::Result<String> {
+    let mut buf = String::new();
+    location
+        .read_item(container, item)
+        .await?
+        .read_to_string(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len());
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}
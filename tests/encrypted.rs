@@ -0,0 +1,107 @@
+#![cfg(feature = "encryption")]
+
+use tokio::io::AsyncReadExt;
+
+async fn read(location: &stow::Location, container: &str, item: &str) -> stow::Result<Vec<u8>> {
+    let mut buf = vec![];
+    location
+        .read_item(container, item)
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+async fn write(location: &stow::Location, container: &str, item: &str, data: Vec<u8>) {
+    location
+        .create_item(container, item, std::io::Cursor::new(data))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_encrypted() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+    let encrypted = stow::Encrypted::new(memory.clone(), stow::Keyring::new("key-1", [1; 32]));
+    let location = stow::Location::Encrypted(encrypted.clone());
+
+    let container = "container-1";
+    location.create_container(container).await?;
+
+    // empty items, items of exactly one chunk and items of several chunks
+    for size in [0, 11, 64 * 1024, 200_000].iter() {
+        let data: Vec<u8> = (0..*size).map(|i| (i % 251) as u8).collect();
+        write(&location, container, "test.bin", data.clone()).await;
+        assert_eq!(read(&location, container, "test.bin").await?, data);
+    }
+
+    // the stored item is encrypted
+    write(&location, container, "test.txt", b"Hello World".to_vec()).await;
+    let raw = read(&memory, container, "test.txt").await?;
+    assert!(!raw.windows(11).any(|w| w == b"Hello World"));
+    assert_eq!(encrypted.key_id(container, "test.txt").await?, "key-1");
+
+    // modified and truncated items fail to read
+    let mut modified = raw.clone();
+    *modified.last_mut().unwrap() ^= 1;
+    write(&memory, container, "modified.txt", modified).await;
+    assert!(read(&location, container, "modified.txt").await.is_err());
+
+    write(
+        &memory,
+        container,
+        "truncated.txt",
+        raw[..raw.len() - 1].to_vec(),
+    )
+    .await;
+    assert!(read(&location, container, "truncated.txt").await.is_err());
+
+    // unencrypted items fail to read
+    write(&memory, container, "plain.txt", b"Hello World".to_vec()).await;
+    assert!(read(&location, container, "plain.txt").await.is_err());
+
+    // a different key with the same id fails to read
+    let wrong =
+        stow::Location::new_encrypted(memory.clone(), stow::Keyring::new("key-1", [2; 32])).await?;
+    assert!(read(&wrong, container, "test.txt").await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_encrypted_rotation() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+    let old =
+        stow::Location::new_encrypted(memory.clone(), stow::Keyring::new("key-1", [1; 32])).await?;
+
+    let container = "container-1";
+    old.create_container(container).await?;
+    write(&old, container, "test.txt", b"Hello World".to_vec()).await;
+
+    // the old key is still used to read older items
+    let new = stow::Encrypted::new(
+        memory.clone(),
+        stow::Keyring::new("key-2", [2; 32]).with_key("key-1", [1; 32]),
+    );
+    let location = stow::Location::Encrypted(new.clone());
+    assert_eq!(
+        read(&location, container, "test.txt").await?,
+        b"Hello World"
+    );
+
+    // rotated items are encrypted with the current key
+    assert!(new.rotate_item(container, "test.txt").await?);
+    assert!(!new.rotate_item(container, "test.txt").await?);
+    assert_eq!(new.key_id(container, "test.txt").await?, "key-2");
+
+    let current =
+        stow::Location::new_encrypted(memory.clone(), stow::Keyring::new("key-2", [2; 32])).await?;
+    assert_eq!(read(&current, container, "test.txt").await?, b"Hello World");
+
+    match read(&old, container, "test.txt").await {
+        Err(stow::StowError::UnknownKeyError(id)) => assert_eq!(id, "key-2"),
+        res => panic!("unexpected result {:?}", res),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "ftp")]
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_ftp() -> stow::Result<()> {
+    if let Err(e) = dotenv::dotenv() {
+        if !e.not_found() {
+            let e: Result<(), dotenv::Error> = Err(e);
+            e.unwrap();
+        }
+    }
+
+    // skipped without an FTP server, e.g. in CI
+    if std::env::var("STOW_FTP_ADDRESS").is_err() {
+        eprintln!("STOW_FTP_ADDRESS is not set, skipping test_ftp");
+        return Ok(());
+    }
+
+    let address = std::env::var("STOW_FTP_ADDRESS")?;
+    let user = std::env::var("STOW_FTP_USER")?;
+    let password = std::env::var("STOW_FTP_PASSWORD")?;
+    let root = std::env::var("STOW_FTP_ROOT")?;
+    let container_1 = std::env::var("STOW_TEST_CONTAINER_1")?;
+    let container_2 = std::env::var("STOW_TEST_CONTAINER_2")?;
+
+    // create a new environment if not avilable
+    // use TLS if requested
+    let ftp = match std::env::var("STOW_FTP_TLS") {
+        Ok(_) => stow::Location::new_ftps(&address, &user, &password, &root).await?,
+        Err(_) => stow::Location::new_ftp(&address, &user, &password, &root).await?,
+    };
+
+    // create new containers if not avilable
+    ftp.create_container(&container_1).await?;
+    ftp.create_container(&container_2).await?;
+
+    assert!(ftp
+        .containers()
+        .await?
+        .contains(&String::from(&container_1)));
+    assert!(ftp
+        .containers()
+        .await?
+        .contains(&String::from(&container_2)));
+
+    // create two test.txt file
+    ftp.create_item(&container_1, "test.txt", reader("Hello World 1").await?)
+        .await?;
+    ftp.create_item(&container_2, "test.txt", reader("Hello World 2").await?)
+        .await?;
+
+    assert!(ftp
+        .items(&container_2)
+        .await?
+        .contains(&String::from("test.txt")));
+
+    // rewrite the test.txt file
+    ftp.create_item(&container_1, "test.txt", reader("Hello World 1 New").await?)
+        .await?;
+
+    // read the test.txt file
+    let mut buf = vec![];
+    ftp.read_item(&container_1, "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World 1 New"[0..], &buf);
+
+    // remove the item.txt in container 2
+    ftp.remove_item(&container_2, "test.txt").await?;
+    assert!(ftp.read_item(&container_2, "test.txt").await.is_err());
+    // remove the container 2
+    ftp.remove_container(&container_2).await?;
+
+    // remove the item.txt in container 1
+    ftp.remove_item(&container_1, "test.txt").await?;
+    assert!(ftp.read_item(&container_1, "test.txt").await.is_err());
+    ftp.remove_container(&container_1).await?;
+
+    Ok(())
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len());
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "guard")]
+
+async fn write(
+    location: &stow::Location,
+    container: &str,
+    item: &str,
+    data: &'static [u8],
+) -> stow::Result<()> {
+    location
+        .create_item(container, item, std::io::Cursor::new(data))
+        .await
+}
+
+fn denied<T>(res: stow::Result<T>) -> bool {
+    matches!(res, Err(stow::StowError::PermissionDenied(_)))
+}
+
+#[tokio::test]
+async fn test_guarded_read_only() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+    memory.create_container("container-1").await?;
+    write(&memory, "container-1", "test.txt", b"Hello World").await?;
+
+    let guarded =
+        stow::Location::new_guarded(memory.clone(), stow::GuardPolicy::read_only()).await?;
+
+    // reading is allowed, changes are refused
+    assert_eq!(guarded.containers().await?, vec!["container-1"]);
+    assert_eq!(guarded.items("container-1").await?, vec!["test.txt"]);
+    assert!(guarded.read_item("container-1", "test.txt").await.is_ok());
+
+    assert!(denied(
+        write(&guarded, "container-1", "test.txt", b"Changed").await
+    ));
+    assert!(denied(guarded.remove_item("container-1", "test.txt").await));
+    assert!(denied(guarded.create_container("container-2").await));
+    assert!(denied(guarded.remove_container("container-1").await));
+    assert_eq!(memory.items("container-1").await?, vec!["test.txt"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_guarded_policy() -> stow::Result<()> {
+    use tokio::io::AsyncReadExt;
+
+    let memory = stow::Location::new_memory().await?;
+    memory.create_container("container-1").await?;
+    memory.create_container("container-2").await?;
+    write(&memory, "container-1", "test.txt", b"Hello World").await?;
+
+    let policy = stow::GuardPolicy {
+        manage_containers: false,
+        containers: Some(vec!["container-1".to_string()]),
+        max_item_size: Some(11),
+        ..Default::default()
+    };
+    let guarded = stow::Location::new_guarded(memory.clone(), policy).await?;
+
+    // only the allowed containers are visible
+    assert_eq!(guarded.containers().await?, vec!["container-1"]);
+    assert!(denied(guarded.items("container-2").await));
+    assert!(denied(
+        write(&guarded, "container-2", "test.txt", b"Hello").await
+    ));
+    assert!(denied(guarded.create_container("container-3").await));
+
+    // too large items are refused, without changing the existing one
+    write(&guarded, "container-1", "small.txt", b"Hello Small").await?;
+    assert!(denied(
+        write(&guarded, "container-1", "test.txt", b"Hello World!").await
+    ));
+
+    let mut buf = vec![];
+    memory
+        .read_item("container-1", "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(buf, b"Hello World");
+
+    guarded.remove_item("container-1", "small.txt").await?;
+    assert_eq!(
+        stow::StowError::PermissionDenied(String::new()).kind(),
+        "permission_denied"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,219 @@
+#![cfg(feature = "http")]
+
+use stow::Adapter;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_http() -> stow::Result<()> {
+    if let Err(e) = dotenv::dotenv() {
+        if !e.not_found() {
+            let e: Result<(), dotenv::Error> = Err(e);
+            e.unwrap();
+        }
+    }
+
+    // skipped without an HTTP server, e.g. in CI
+    if std::env::var("STOW_HTTP_URL").is_err() {
+        eprintln!("STOW_HTTP_URL is not set, skipping test_http");
+        return Ok(());
+    }
+
+    let url = std::env::var("STOW_HTTP_URL")?;
+    let container = std::env::var("STOW_HTTP_CONTAINER")?;
+    let item = std::env::var("STOW_HTTP_ITEM")?;
+
+    // the index file is optional
+    let mut http = stow::Http::new(&url).await?;
+    if let Ok(index) = std::env::var("STOW_HTTP_INDEX") {
+        http = http.with_index(&index);
+
+        assert!(http.containers().await?.contains(&container));
+        assert!(http.items(&container).await?.contains(&item));
+    }
+
+    // read the full item
+    let mut buf = vec![];
+    http.read_item(&container, &item)
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert!(!buf.is_empty());
+
+    // the size needs to match the read item
+    let info = http.stat_item(&container, &item).await?;
+    assert_eq!(info.size, Some(buf.len() as u64));
+
+    // read the first byte only
+    let mut first = vec![];
+    http.read_item_range(&container, &item, 0..1)
+        .await?
+        .read_to_end(&mut first)
+        .await?;
+    assert_eq!(&buf[0..1], &first);
+
+    // writing is not possible
+    let http = stow::Location::Http(http);
+    assert!(matches!(
+        http.create_item(&container, "test.txt", reader("Hello World").await?)
+            .await,
+        Err(stow::StowError::Unsupported)
+    ));
+    assert!(matches!(
+        http.remove_item(&container, &item).await,
+        Err(stow::StowError::Unsupported)
+    ));
+
+    Ok(())
+}
+
+/// Serve the files on a local port below `/files/`, with ETags and byte ranges
+/// like a static file server. Ranges of `norange.txt` are ignored
+async fn file_server(files: &'static [(&'static str, &'static str)]) -> stow::Result<String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/files/", listener.local_addr()?);
+
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0; 4096];
+            let n = socket.read(&mut buf).await.unwrap_or_default();
+            let request = String::from_utf8_lossy(&buf[..n]).to_string();
+
+            let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+            let method = request_line.next().unwrap_or_default();
+            let path = request_line.next().unwrap_or_default();
+            let range = request
+                .lines()
+                .filter_map(|l| l.split_once(':'))
+                .find(|(n, _)| n.eq_ignore_ascii_case("range"))
+                .and_then(|(_, v)| v.trim().strip_prefix("bytes="))
+                .and_then(|r| r.split_once('-'))
+                .and_then(|(s, e)| Some((s.parse::<usize>().ok()?, e.parse::<usize>().ok()?)));
+
+            let file = files
+                .iter()
+                .find(|(name, _)| format!("/files/{}", name) == path);
+            let (status, headers, body) = match file {
+                None => ("404 Not Found", String::new(), ""),
+                Some((name, data)) => {
+                    let headers = format!(
+                        "content-type: text/plain\r\netag: \"{}\"\r\n\
+                         last-modified: Mon, 19 Oct 2026 10:00:00 GMT\r\n",
+                        data.len()
+                    );
+                    match range {
+                        Some((start, end)) if !name.ends_with("norange.txt") => {
+                            let end = end.min(data.len() - 1);
+                            let headers = format!(
+                                "{}content-range: bytes {}-{}/{}\r\n",
+                                headers,
+                                start,
+                                end,
+                                data.len()
+                            );
+                            ("206 Partial Content", headers, &data[start..=end])
+                        }
+                        _ => ("200 OK", headers, *data),
+                    }
+                }
+            };
+
+            // HEAD requests get the length of the body, but not the body
+            let response = format!(
+                "HTTP/1.1 {}\r\n{}content-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                headers,
+                body.len(),
+                if method == "HEAD" { "" } else { body }
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    Ok(url)
+}
+
+#[tokio::test]
+async fn test_http_mock() -> stow::Result<()> {
+    let url = file_server(&[
+        ("index.txt", "container-1\ncontainer-2\n"),
+        ("container-1/index.txt", "test.txt\n\n  other.txt \n"),
+        ("container-1/test.txt", "Hello World"),
+        ("container-1/other.txt", "Hello Mars"),
+        ("container-2/norange.txt", "Hello World"),
+    ])
+    .await?;
+
+    // listing is only supported with an index file
+    let http = stow::Http::new(&url).await?;
+    assert!(matches!(
+        http.containers().await,
+        Err(stow::StowError::Unsupported)
+    ));
+
+    let http = http.with_index("index.txt");
+    assert_eq!(http.containers().await?, vec!["container-1", "container-2"]);
+    assert_eq!(
+        http.items("container-1").await?,
+        vec!["test.txt", "other.txt"]
+    );
+    assert!(http.items("container-3").await.is_err());
+
+    let mut buf = String::new();
+    http.read_item("container-1", "test.txt")
+        .await?
+        .read_to_string(&mut buf)
+        .await?;
+    assert_eq!(buf, "Hello World");
+    assert!(http.read_item("container-1", "missing.txt").await.is_err());
+
+    // only the requested bytes are read
+    let mut buf = String::new();
+    http.read_item_range("container-1", "test.txt", 6..11)
+        .await?
+        .read_to_string(&mut buf)
+        .await?;
+    assert_eq!(buf, "World");
+
+    let mut buf = String::new();
+    http.read_item_range("container-1", "test.txt", 3..3)
+        .await?
+        .read_to_string(&mut buf)
+        .await?;
+    assert!(buf.is_empty());
+
+    // a server, which ignores the range, would send the full item
+    assert!(matches!(
+        http.read_item_range("container-2", "norange.txt", 0..5)
+            .await
+            .map(|_| ()),
+        Err(stow::StowError::Unsupported)
+    ));
+
+    let info = http.stat_item("container-1", "test.txt").await?;
+    assert_eq!(info.size, Some(11));
+    assert_eq!(info.etag.as_deref(), Some("\"11\""));
+    assert_eq!(info.content_type.as_deref(), Some("text/plain"));
+    assert!(info.last_modified.is_some());
+    assert!(http.stat_item("container-1", "missing.txt").await.is_err());
+
+    // writing is not possible
+    let unsupported = |res: stow::Result<()>| matches!(res, Err(stow::StowError::Unsupported));
+    assert!(unsupported(http.create_container("container-3").await));
+    assert!(unsupported(http.remove_container("container-1").await));
+    assert!(unsupported(
+        http.create_item("container-1", "new.txt", reader("Hello World").await?)
+            .await
+    ));
+    assert!(unsupported(
+        http.remove_item("container-1", "test.txt").await
+    ));
+
+    Ok(())
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len());
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}
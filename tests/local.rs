@@ -3,7 +3,7 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 #[tokio::test]
 async fn test_local() -> stow::Result<()> {
     // create a new environment if not avilable
-    let mut local = stow::Location::new_local("./data").await?;
+    let local = stow::Location::new_local("./data").await?;
 
     let container_1 = "container-1";
     let container_2 = "container-2";
@@ -57,6 +57,47 @@ async fn test_local() -> stow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_local_shared() -> stow::Result<()> {
+    // one location shared between multiple tasks
+    let local = std::sync::Arc::new(stow::Location::new_local("./data").await?);
+
+    let container = "container-shared";
+    local.create_container(container).await?;
+
+    // write and read items concurrently
+    let mut tasks = vec![];
+    for i in 0..4 {
+        let local = local.clone();
+        tasks.push(tokio::spawn(async move {
+            let item = format!("test-{}.txt", i);
+            local
+                .create_item(container, &item, reader(&item).await?)
+                .await?;
+
+            let mut buf = vec![];
+            local
+                .read_item(container, &item)
+                .await?
+                .read_to_end(&mut buf)
+                .await?;
+            assert_eq!(item.as_bytes(), &buf[..]);
+
+            Ok::<_, stow::StowError>(())
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap()?;
+    }
+
+    assert_eq!(local.items(container).await?.len(), 4);
+
+    // remove the container
+    local.remove_container(container).await?;
+
+    Ok(())
+}
+
 async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
     let (mut send, recv) = tokio::io::duplex(data.len());
     send.write_all(data.as_bytes()).await?;
@@ -0,0 +1,108 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_memory() -> stow::Result<()> {
+    // create a new empty in-memory location
+    let memory = stow::Location::new_memory().await?;
+
+    let container_1 = "container-1";
+    let container_2 = "container-2";
+
+    // create new containers if not avilable
+    memory.create_container(container_1).await?;
+    memory.create_container(container_2).await?;
+    assert!(memory
+        .containers()
+        .await?
+        .contains(&String::from(container_1)));
+    assert!(memory
+        .containers()
+        .await?
+        .contains(&String::from(container_2)));
+
+    // create two test.txt file
+    memory
+        .create_item(container_1, "test.txt", reader("Hello World 1").await?)
+        .await?;
+    memory
+        .create_item(container_2, "test.txt", reader("Hello World 2").await?)
+        .await?;
+    assert!(memory
+        .items(container_2)
+        .await?
+        .contains(&String::from("test.txt")));
+
+    // rewrite the test.txt file
+    memory
+        .create_item(container_1, "test.txt", reader("Hello World 1 New").await?)
+        .await?;
+
+    // read the test.txt file
+    let mut buf = vec![];
+    memory
+        .read_item(container_1, "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World 1 New"[0..], &buf);
+
+    // remove the item.txt in container 2
+    memory.remove_item(container_2, "test.txt").await?;
+    assert!(memory.read_item(container_2, "test.txt").await.is_err());
+
+    // remove the container
+    memory.remove_container(container_2).await?;
+    memory.remove_container(container_1).await?;
+    assert!(memory.containers().await?.is_empty());
+    assert!(memory.items(container_1).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_shared() -> stow::Result<()> {
+    // one location shared between multiple tasks
+    let memory = std::sync::Arc::new(stow::Location::new_memory().await?);
+
+    let container = "container-shared";
+    memory.create_container(container).await?;
+
+    // write and read items concurrently
+    let mut tasks = vec![];
+    for i in 0..4 {
+        let memory = memory.clone();
+        tasks.push(tokio::spawn(async move {
+            let item = format!("test-{}.txt", i);
+            memory
+                .create_item(container, &item, reader(&item).await?)
+                .await?;
+
+            let mut buf = vec![];
+            memory
+                .read_item(container, &item)
+                .await?
+                .read_to_end(&mut buf)
+                .await?;
+            assert_eq!(item.as_bytes(), &buf[..]);
+
+            Ok::<_, stow::StowError>(())
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap()?;
+    }
+
+    assert_eq!(memory.items(container).await?.len(), 4);
+
+    // remove the container
+    memory.remove_container(container).await?;
+
+    Ok(())
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len());
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}
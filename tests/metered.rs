@@ -0,0 +1,130 @@
+#![cfg(feature = "metrics")]
+
+use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+use tokio::io::AsyncReadExt;
+
+type Metrics = Vec<(String, Vec<(String, String)>, DebugValue)>;
+
+/// Take a snapshot of the metrics with their names and labels, which resets their values
+fn snapshot(snapshotter: &Snapshotter) -> Metrics {
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| {
+            let key = key.key();
+            let labels = key
+                .labels()
+                .map(|l| (l.key().to_string(), l.value().to_string()))
+                .collect();
+            (key.name().to_string(), labels, value)
+        })
+        .collect()
+}
+
+/// Get the value of the metric, which has at least the given labels
+fn value<'a>(metrics: &'a Metrics, name: &str, labels: &[(&str, &str)]) -> Option<&'a DebugValue> {
+    metrics
+        .iter()
+        .find(|(n, l, _)| {
+            n == name
+                && labels
+                    .iter()
+                    .all(|(k, v)| l.iter().any(|(lk, lv)| lk == k && lv == v))
+        })
+        .map(|(_, _, value)| value)
+}
+
+#[tokio::test]
+async fn test_metered() -> stow::Result<()> {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    recorder.install().unwrap();
+
+    let metered = stow::Location::new_metered(stow::Location::new_memory().await?).await?;
+    metered.create_container("container-1").await?;
+    metered
+        .create_item(
+            "container-1",
+            "test.txt",
+            std::io::Cursor::new(b"Hello World"),
+        )
+        .await?;
+
+    let mut buf = vec![];
+    metered
+        .read_item("container-1", "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert!(metered
+        .read_item("container-1", "missing.txt")
+        .await
+        .is_err());
+
+    let snapshot = snapshot(&snapshotter);
+
+    // operations are counted by backend, operation and outcome
+    let labels = [
+        ("backend", "memory"),
+        ("operation", "create_item"),
+        ("outcome", "ok"),
+    ];
+    assert_eq!(
+        value(&snapshot, stow::METRIC_OPERATIONS, &labels),
+        Some(&DebugValue::Counter(1))
+    );
+    let labels = [("operation", "read_item"), ("outcome", "error")];
+    assert_eq!(
+        value(&snapshot, stow::METRIC_OPERATIONS, &labels),
+        Some(&DebugValue::Counter(1))
+    );
+
+    // errors are counted by their kind
+    let labels = [("operation", "read_item"), ("kind", "not_found")];
+    assert_eq!(
+        value(&snapshot, stow::METRIC_ERRORS, &labels),
+        Some(&DebugValue::Counter(1))
+    );
+
+    // transferred bytes
+    let labels = [("backend", "memory")];
+    assert_eq!(
+        value(&snapshot, stow::METRIC_BYTES_WRITTEN, &labels),
+        Some(&DebugValue::Counter(11))
+    );
+    assert_eq!(
+        value(&snapshot, stow::METRIC_BYTES_READ, &labels),
+        Some(&DebugValue::Counter(11))
+    );
+
+    // every operation has a duration
+    let labels = [("operation", "create_container")];
+    match value(&snapshot, stow::METRIC_DURATION, &labels) {
+        Some(DebugValue::Histogram(values)) => assert_eq!(values.len(), 1),
+        v => panic!("unexpected duration {:?}", v),
+    }
+
+    // wrappers are skipped, the backend storing the data is the label
+    #[cfg(feature = "retry")]
+    {
+        let retry = stow::Location::new_retry(stow::Location::new_memory().await?).await?;
+        let metered = stow::Location::new_metered(retry).await?;
+        metered.create_container("container-1").await?;
+
+        let wrapped = crate::snapshot(&snapshotter);
+        let labels = [
+            ("backend", "memory"),
+            ("operation", "create_container"),
+            ("outcome", "ok"),
+        ];
+        assert_eq!(
+            value(&wrapped, stow::METRIC_OPERATIONS, &labels),
+            Some(&DebugValue::Counter(1))
+        );
+        let labels = [("backend", "retry")];
+        assert_eq!(value(&wrapped, stow::METRIC_OPERATIONS, &labels), None);
+    }
+
+    Ok(())
+}
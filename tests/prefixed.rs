@@ -0,0 +1,84 @@
+#![cfg(feature = "prefix")]
+
+use tokio::io::AsyncReadExt;
+
+async fn read(location: &stow::Location, container: &str, item: &str) -> stow::Result<Vec<u8>> {
+    let mut buf = vec![];
+    location
+        .read_item(container, item)
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+async fn write(location: &stow::Location, container: &str, item: &str, data: &'static [u8]) {
+    location
+        .create_item(container, item, std::io::Cursor::new(data))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_prefixed() -> stow::Result<()> {
+    let memory = stow::Location::new_memory().await?;
+    let tenant_a = stow::Location::new_prefixed(memory.clone(), "shared", "tenant-a").await?;
+    let tenant_b = stow::Location::new_prefixed(memory.clone(), "shared", "tenant-b").await?;
+
+    tenant_a.create_container("container-1").await?;
+    write(&tenant_a, "container-1", "test.txt", b"Hello A1").await;
+    write(&tenant_a, "container-2", "test.txt", b"Hello A2").await;
+    write(&tenant_b, "container-1", "test.txt", b"Hello B1").await;
+    write(&memory, "shared", "other.txt", b"Hello").await;
+
+    // the items are stored under the prefix in the shared container
+    let mut items = memory.items("shared").await?;
+    items.sort();
+    assert_eq!(
+        items,
+        vec![
+            "other.txt",
+            "tenant-a.container-1.test.txt",
+            "tenant-a.container-2.test.txt",
+            "tenant-b.container-1.test.txt",
+        ]
+    );
+
+    // every view only sees its own containers and items
+    assert_eq!(
+        tenant_a.containers().await?,
+        vec!["container-1", "container-2"]
+    );
+    assert_eq!(tenant_b.containers().await?, vec!["container-1"]);
+    assert_eq!(tenant_a.items("container-1").await?, vec!["test.txt"]);
+    assert_eq!(
+        read(&tenant_a, "container-1", "test.txt").await?,
+        b"Hello A1"
+    );
+    assert_eq!(
+        read(&tenant_b, "container-1", "test.txt").await?,
+        b"Hello B1"
+    );
+    assert!(tenant_b.read_item("container-2", "test.txt").await.is_err());
+
+    // names which could leave the prefix are refused
+    for item in ["test./../other.txt", "test.\\other.txt", "test..txt"].iter() {
+        match tenant_a.read_item("container-1", item).await {
+            Err(stow::StowError::InvalidNameError(_)) => {}
+            _ => panic!("{} was not refused", item),
+        }
+    }
+    assert!(stow::Location::new_prefixed(memory.clone(), "shared", "..")
+        .await
+        .is_err());
+
+    // removing a container removes its items
+    tenant_a.remove_container("container-1").await?;
+    assert_eq!(tenant_a.containers().await?, vec!["container-2"]);
+    assert_eq!(tenant_b.containers().await?, vec!["container-1"]);
+
+    tenant_a.remove_item("container-2", "test.txt").await?;
+    assert!(tenant_a.containers().await?.is_empty());
+
+    Ok(())
+}
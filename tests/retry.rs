@@ -0,0 +1,120 @@
+#![cfg(all(feature = "retry", feature = "http"))]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use stow::Adapter;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Serve `/container/test.txt` on a local port, failing the first requests with the given status
+async fn flaky_server(failures: usize, status: &'static str) -> (String, Arc<AtomicUsize>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+
+    let counter = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0; 4096];
+            let _ = socket.read(&mut buf).await;
+
+            let response = if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                format!(
+                    "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    status
+                )
+            } else {
+                "HTTP/1.1 200 OK\r\ncontent-length: 11\r\nconnection: close\r\n\r\nHello World"
+                    .to_string()
+            };
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    (url, requests)
+}
+
+fn policy(max_attempts: u32) -> stow::RetryPolicy {
+    stow::RetryPolicy {
+        max_attempts,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(10),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_retry() -> stow::Result<()> {
+    // transient errors are retried until the request succeeds
+    let (url, requests) = flaky_server(2, "503 Service Unavailable").await;
+    let retry = stow::Retry::new(stow::Location::new_http(&url).await?).with_policy(policy(5));
+
+    let mut buf = vec![];
+    retry
+        .read_item("container", "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World"[..], &buf);
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    // the number of attempts is limited
+    let (url, requests) = flaky_server(10, "429 Too Many Requests").await;
+    let retry = stow::Retry::new(stow::Location::new_http(&url).await?).with_policy(policy(3));
+    assert!(retry.read_item("container", "test.txt").await.is_err());
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    // other errors are returned immediately
+    let (url, requests) = flaky_server(10, "404 Not Found").await;
+    let retry = stow::Retry::new(stow::Location::new_http(&url).await?).with_policy(policy(5));
+    assert!(retry.read_item("container", "test.txt").await.is_err());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // no retry is started after the deadline
+    let (url, requests) = flaky_server(10, "500 Internal Server Error").await;
+    let retry =
+        stow::Retry::new(stow::Location::new_http(&url).await?).with_policy(stow::RetryPolicy {
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+            jitter: false,
+            deadline: Some(Duration::from_millis(120)),
+            ..policy(10)
+        });
+    assert!(retry.read_item("container", "test.txt").await.is_err());
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retry_location() -> stow::Result<()> {
+    // writes are passed through to the wrapped location
+    let memory = stow::Location::new_memory().await?;
+    let retry = stow::Location::new_retry(memory.clone()).await?;
+
+    retry.create_container("container-1").await?;
+    retry
+        .create_item(
+            "container-1",
+            "test.txt",
+            std::io::Cursor::new(b"Hello World"),
+        )
+        .await?;
+
+    let mut buf = vec![];
+    memory
+        .read_item("container-1", "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World"[..], &buf);
+
+    assert!(!stow::is_retryable(&stow::StowError::ItemTypMissing));
+    assert!(stow::is_retryable(&stow::StowError::HttpStatusError(503)));
+
+    retry.remove_item("container-1", "test.txt").await?;
+    retry.remove_container("container-1").await?;
+    assert!(memory.containers().await?.is_empty());
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "s3")]
+
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 #[tokio::test]
@@ -15,7 +17,7 @@ async fn test_s3() -> stow::Result<()> {
     let container_2 = std::env::var("STOW_TEST_CONTAINER_2")?;
 
     // create a new environment if not avilable
-    let mut aws3 = stow::Location::new_s3("eu-central-1", &access_key, &secret_key).await?;
+    let aws3 = stow::Location::new_s3("eu-central-1", &access_key, &secret_key).await?;
 
     // create new containers if not avilable
     aws3.create_container(&container_1).await?;
@@ -0,0 +1,92 @@
+#![cfg(feature = "sftp")]
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_sftp() -> stow::Result<()> {
+    if let Err(e) = dotenv::dotenv() {
+        if !e.not_found() {
+            let e: Result<(), dotenv::Error> = Err(e);
+            e.unwrap();
+        }
+    }
+
+    // skipped without an SFTP server, e.g. in CI
+    if std::env::var("STOW_SFTP_ADDRESS").is_err() {
+        eprintln!("STOW_SFTP_ADDRESS is not set, skipping test_sftp");
+        return Ok(());
+    }
+
+    let address = std::env::var("STOW_SFTP_ADDRESS")?;
+    let user = std::env::var("STOW_SFTP_USER")?;
+    let root = std::env::var("STOW_SFTP_ROOT")?;
+    let container_1 = std::env::var("STOW_TEST_CONTAINER_1")?;
+    let container_2 = std::env::var("STOW_TEST_CONTAINER_2")?;
+
+    // create a new environment if not avilable
+    // authenticate with a private key if given, otherwise with a password
+    let auth = match std::env::var("STOW_SFTP_KEY") {
+        Ok(private_key) => stow::SftpAuth::Key {
+            private_key,
+            passphrase: std::env::var("STOW_SFTP_PASSPHRASE").ok(),
+        },
+        Err(_) => stow::SftpAuth::Password(std::env::var("STOW_SFTP_PASSWORD")?),
+    };
+    let sftp = stow::Location::new_sftp(&address, &user, auth, &root).await?;
+
+    // create new containers if not avilable
+    sftp.create_container(&container_1).await?;
+    sftp.create_container(&container_2).await?;
+
+    assert!(sftp
+        .containers()
+        .await?
+        .contains(&String::from(&container_1)));
+    assert!(sftp
+        .containers()
+        .await?
+        .contains(&String::from(&container_2)));
+
+    // create two test.txt file
+    sftp.create_item(&container_1, "test.txt", reader("Hello World 1").await?)
+        .await?;
+    sftp.create_item(&container_2, "test.txt", reader("Hello World 2").await?)
+        .await?;
+
+    assert!(sftp
+        .items(&container_2)
+        .await?
+        .contains(&String::from("test.txt")));
+
+    // rewrite the test.txt file
+    sftp.create_item(&container_1, "test.txt", reader("Hello World 1 New").await?)
+        .await?;
+
+    // read the test.txt file
+    let mut buf = vec![];
+    sftp.read_item(&container_1, "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World 1 New"[0..], &buf);
+
+    // remove the item.txt in container 2
+    sftp.remove_item(&container_2, "test.txt").await?;
+    assert!(sftp.read_item(&container_2, "test.txt").await.is_err());
+    // remove the container 2
+    sftp.remove_container(&container_2).await?;
+
+    // remove the item.txt in container 1
+    sftp.remove_item(&container_1, "test.txt").await?;
+    assert!(sftp.read_item(&container_1, "test.txt").await.is_err());
+    sftp.remove_container(&container_1).await?;
+
+    Ok(())
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len());
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}
@@ -0,0 +1,166 @@
+#![cfg(feature = "sqlite")]
+
+use stow::Adapter;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_sqlite() -> stow::Result<()> {
+    tokio::fs::create_dir_all("./data").await?;
+
+    // create a new database if not avilable
+    let sqlite = stow::Location::new_sqlite("./data/test.sqlite").await?;
+
+    let container_1 = "container-1";
+    let container_2 = "container-2";
+
+    // create new containers if not avilable
+    sqlite.create_container(container_1).await?;
+    sqlite.create_container(container_2).await?;
+    sqlite.create_container(container_2).await?;
+    assert!(sqlite
+        .containers()
+        .await?
+        .contains(&String::from(container_1)));
+    assert!(sqlite
+        .containers()
+        .await?
+        .contains(&String::from(container_2)));
+
+    // create two test.txt file
+    sqlite
+        .create_item(container_1, "test.txt", reader("Hello World 1").await?)
+        .await?;
+    sqlite
+        .create_item(container_2, "test.txt", reader("Hello World 2").await?)
+        .await?;
+    assert!(sqlite
+        .items(container_2)
+        .await?
+        .contains(&String::from("test.txt")));
+
+    // items can only be created in available containers
+    assert!(sqlite
+        .create_item("container-missing", "test.txt", reader("Hello").await?)
+        .await
+        .is_err());
+
+    // rewrite the test.txt file
+    sqlite
+        .create_item(container_1, "test.txt", reader("Hello World 1 New").await?)
+        .await?;
+
+    // read the test.txt file
+    let mut buf = vec![];
+    sqlite
+        .read_item(container_1, "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World 1 New"[0..], &buf);
+
+    // remove the item.txt in container 2
+    sqlite.remove_item(container_2, "test.txt").await?;
+    assert!(sqlite.read_item(container_2, "test.txt").await.is_err());
+
+    // remove the container
+    sqlite.remove_container(container_2).await?;
+    sqlite.remove_container(container_1).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_chunks() -> stow::Result<()> {
+    tokio::fs::create_dir_all("./data").await?;
+
+    // small chunks to store the item in multiple rows
+    let sqlite = stow::Sqlite::new("./data/chunks.sqlite")
+        .await?
+        .with_chunk_size(4);
+
+    let container = "container-chunks";
+    sqlite.create_container(container).await?;
+
+    let data = "Hello World, stored in chunks";
+    sqlite
+        .create_item(container, "test.txt", reader(data).await?)
+        .await?;
+
+    let mut buf = String::new();
+    sqlite
+        .read_item(container, "test.txt")
+        .await?
+        .read_to_string(&mut buf)
+        .await?;
+    assert_eq!(data, buf);
+
+    // an empty item has no chunks
+    sqlite
+        .create_item(container, "empty.txt", reader("").await?)
+        .await?;
+    let mut buf = vec![];
+    sqlite
+        .read_item(container, "empty.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert!(buf.is_empty());
+
+    sqlite.remove_container(container).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_aborted_upload() -> stow::Result<()> {
+    tokio::fs::create_dir_all("./data").await?;
+
+    let sqlite = stow::Sqlite::new("./data/aborted.sqlite")
+        .await?
+        .with_chunk_size(4);
+    let container = "container-aborted";
+    sqlite.create_container(container).await?;
+    sqlite
+        .create_item(container, "test.txt", reader("Hello World").await?)
+        .await?;
+
+    // the upload waits for the rest of the data, other writers don't wait for it
+    let (mut send, recv) = tokio::io::duplex(64);
+    send.write_all(b"Hello Mars").await?;
+    let upload = Box::pin(sqlite.create_item(container, "test.txt", recv));
+    let other = Box::pin(sqlite.create_item(container, "other.txt", reader("Hello").await?));
+    match futures::future::select(upload, other).await {
+        futures::future::Either::Right((res, upload)) => {
+            res?;
+            // abort the upload
+            drop(upload);
+        }
+        futures::future::Either::Left(_) => panic!("the upload finished without its end"),
+    }
+    drop(send);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // the item is kept and the staged chunks are removed
+    let mut buf = String::new();
+    sqlite
+        .read_item(container, "test.txt")
+        .await?
+        .read_to_string(&mut buf)
+        .await?;
+    assert_eq!(buf, "Hello World");
+    assert_eq!(
+        sqlite.items(container).await?,
+        vec!["other.txt", "test.txt"]
+    );
+
+    sqlite.remove_container(container).await?;
+
+    Ok(())
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len().max(1));
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}
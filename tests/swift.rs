@@ -0,0 +1,536 @@
+#![cfg(feature = "swift")]
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_swift() -> stow::Result<()> {
+    if let Err(e) = dotenv::dotenv() {
+        if !e.not_found() {
+            let e: Result<(), dotenv::Error> = Err(e);
+            e.unwrap();
+        }
+    }
+
+    // skipped without a Swift cluster, e.g. in CI
+    if std::env::var("STOW_SWIFT_AUTH_URL").is_err() {
+        eprintln!("STOW_SWIFT_AUTH_URL is not set, skipping test_swift");
+        return Ok(());
+    }
+
+    let auth = stow::SwiftAuth {
+        auth_url: std::env::var("STOW_SWIFT_AUTH_URL")?,
+        user: std::env::var("STOW_SWIFT_USER")?,
+        password: std::env::var("STOW_SWIFT_PASSWORD")?,
+        domain: std::env::var("STOW_SWIFT_DOMAIN").unwrap_or_else(|_| "Default".into()),
+        project: std::env::var("STOW_SWIFT_PROJECT")?,
+        region: std::env::var("STOW_SWIFT_REGION").ok(),
+    };
+    let container_1 = std::env::var("STOW_TEST_CONTAINER_1")?;
+    let container_2 = std::env::var("STOW_TEST_CONTAINER_2")?;
+
+    // create a new environment if not avilable
+    // use tiny segments to test the large object upload
+    let swift = stow::Location::Swift(stow::Swift::new(auth).await?.with_segment_size(4));
+
+    // create new containers if not avilable
+    swift.create_container(&container_1).await?;
+    swift.create_container(&container_2).await?;
+
+    assert!(swift
+        .containers()
+        .await?
+        .contains(&String::from(&container_1)));
+    assert!(swift
+        .containers()
+        .await?
+        .contains(&String::from(&container_2)));
+
+    // create two test.txt file
+    swift
+        .create_item(&container_1, "test.txt", reader("Hello World 1").await?)
+        .await?;
+    swift
+        .create_item(&container_2, "test.txt", reader("Hello World 2").await?)
+        .await?;
+
+    assert!(swift
+        .items(&container_2)
+        .await?
+        .contains(&String::from("test.txt")));
+
+    // rewrite the test.txt file
+    swift
+        .create_item(&container_1, "test.txt", reader("Hello World 1 New").await?)
+        .await?;
+
+    // read the test.txt file
+    let mut buf = vec![];
+    swift
+        .read_item(&container_1, "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World 1 New"[0..], &buf);
+
+    // remove the item.txt in container 2
+    swift.remove_item(&container_2, "test.txt").await?;
+    assert!(swift.read_item(&container_2, "test.txt").await.is_err());
+    // remove the container 2
+    swift.remove_container(&container_2).await?;
+
+    // remove the item.txt in container 1
+    swift.remove_item(&container_1, "test.txt").await?;
+    assert!(swift.read_item(&container_1, "test.txt").await.is_err());
+    swift.remove_container(&container_1).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_swift_mock() -> stow::Result<()> {
+    use stow::Adapter;
+
+    let (url, state) = mock_server().await?;
+    let auth = stow::SwiftAuth {
+        auth_url: format!("{}/v3", url),
+        user: "user".to_string(),
+        password: "password".to_string(),
+        domain: "Default".to_string(),
+        project: "project".to_string(),
+        region: Some("RegionTwo".to_string()),
+    };
+    let swift = stow::Swift::new(auth.clone()).await?.with_segment_size(5);
+    assert_eq!(state.lock().unwrap().tokens, 1);
+
+    swift.create_container("container-1").await?;
+    swift.create_container("container-2").await?;
+    assert_eq!(
+        swift.containers().await?,
+        vec!["container-1", "container-2"]
+    );
+
+    // small items are uploaded as one object
+    swift
+        .create_item("container-1", "small.txt", reader("Hello").await?)
+        .await?;
+    assert_eq!(read(&swift, "container-1", "small.txt").await?, "Hello");
+
+    // large items are uploaded as segments of 5 bytes and a manifest
+    swift
+        .create_item("container-1", "large.txt", reader("Hello World 1").await?)
+        .await?;
+    assert_eq!(
+        read(&swift, "container-1", "large.txt").await?,
+        "Hello World 1"
+    );
+    assert_eq!(
+        state.lock().unwrap().segments("container-1", "large.txt"),
+        3
+    );
+    assert_eq!(state.lock().unwrap().objects("container-1_segments"), 3);
+
+    // the segments container is hidden and the listings are paged
+    assert_eq!(
+        swift.containers().await?,
+        vec!["container-1", "container-2"]
+    );
+    assert_eq!(
+        swift.items("container-1").await?,
+        vec!["large.txt", "small.txt"]
+    );
+
+    // the segments of overwritten large items are removed
+    swift
+        .create_item(
+            "container-1",
+            "large.txt",
+            reader("Hello World 1 New").await?,
+        )
+        .await?;
+    assert_eq!(
+        read(&swift, "container-1", "large.txt").await?,
+        "Hello World 1 New"
+    );
+    assert_eq!(state.lock().unwrap().objects("container-1_segments"), 4);
+
+    swift
+        .create_item("container-1", "large.txt", reader("Hi").await?)
+        .await?;
+    assert_eq!(read(&swift, "container-1", "large.txt").await?, "Hi");
+    assert_eq!(
+        state.lock().unwrap().segments("container-1", "large.txt"),
+        0
+    );
+    assert_eq!(state.lock().unwrap().objects("container-1_segments"), 0);
+
+    // an expired token is renewed once, uploads are repeated with their data
+    state.lock().unwrap().expired = true;
+    swift
+        .create_item("container-2", "large.txt", reader("Hello World 2").await?)
+        .await?;
+    assert_eq!(state.lock().unwrap().tokens, 2);
+    assert_eq!(
+        read(&swift, "container-2", "large.txt").await?,
+        "Hello World 2"
+    );
+
+    // removing a large item removes its segments
+    swift.remove_item("container-2", "large.txt").await?;
+    assert!(swift.read_item("container-2", "large.txt").await.is_err());
+    assert_eq!(state.lock().unwrap().objects("container-2_segments"), 0);
+
+    // containers are removed with their items and segments
+    swift
+        .create_item("container-1", "large.txt", reader("Hello World 1").await?)
+        .await?;
+    swift.remove_container("container-1").await?;
+    swift.remove_container("container-2").await?;
+    assert!(swift.containers().await?.is_empty());
+    assert!(state.lock().unwrap().containers.is_empty());
+
+    // wrong credentials are refused
+    let auth = stow::SwiftAuth {
+        password: "wrong".to_string(),
+        ..auth
+    };
+    assert!(stow::Swift::new(auth).await.is_err());
+
+    Ok(())
+}
+
+/// Stored object, large objects have the paths and sizes of their segments instead of data
+enum Object {
+    Data(Vec<u8>),
+    Manifest(Vec<(String, usize)>),
+}
+
+/// Containers and objects of the mock server
+#[derive(Default)]
+struct State {
+    containers: BTreeMap<String, BTreeMap<String, Object>>,
+    /// Number of issued tokens, only the latest one is valid
+    tokens: usize,
+    expired: bool,
+}
+
+impl State {
+    fn objects(&self, container: &str) -> usize {
+        self.containers.get(container).map(|o| o.len()).unwrap_or(0)
+    }
+
+    fn segments(&self, container: &str, name: &str) -> usize {
+        match self.containers.get(container).and_then(|o| o.get(name)) {
+            Some(Object::Manifest(segments)) => segments.len(),
+            _ => 0,
+        }
+    }
+
+    fn data(&self, container: &str, name: &str) -> Option<Vec<u8>> {
+        match self.containers.get(container)?.get(name)? {
+            Object::Data(data) => Some(data.clone()),
+            Object::Manifest(segments) => segments
+                .iter()
+                .map(|(path, _)| {
+                    let (container, name) = path.trim_start_matches('/').split_once('/')?;
+                    self.data(container, name)
+                })
+                .collect::<Option<Vec<_>>>()
+                .map(|d| d.concat()),
+        }
+    }
+}
+
+/// Serve Keystone and the Swift object store of the project `AUTH_test` on a local port
+async fn mock_server() -> stow::Result<(String, Arc<Mutex<State>>)> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let state = Arc::new(Mutex::new(State::default()));
+
+    let (u, s) = (url.clone(), state.clone());
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let (status, headers, body) = match read_request(&mut socket).await {
+                Some(request) => handle(&mut s.lock().unwrap(), &u, request),
+                None => continue,
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\n{}content-length: {}\r\nconnection: close\r\n\r\n",
+                status,
+                headers,
+                body.len()
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+            let _ = socket.write_all(&body).await;
+        }
+    });
+
+    Ok((url, state))
+}
+
+/// Method, path, lowercase headers and body of a request
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+async fn read_request(socket: &mut tokio::net::TcpStream) -> Option<Request> {
+    let mut buf = vec![];
+    let end = loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+        let mut chunk = [0; 4096];
+        match socket.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(n, v)| (n.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(0);
+    let mut body = buf.split_off(end + 4);
+    while body.len() < length {
+        let mut chunk = [0; 4096];
+        match socket.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+        }
+    }
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+type Response = (&'static str, String, Vec<u8>);
+
+fn status(status: &'static str) -> Response {
+    (status, String::new(), vec![])
+}
+
+fn json(value: serde_json::Value) -> Response {
+    ("200 OK", String::new(), value.to_string().into_bytes())
+}
+
+/// Return the names after the marker, two per page
+fn page<'a>(names: impl Iterator<Item = &'a String>, marker: Option<&String>) -> Response {
+    let page: Vec<_> = names
+        .filter(|n| marker.map(|m| *n > m).unwrap_or(true))
+        .take(2)
+        .map(|n| serde_json::json!({ "name": n }))
+        .collect();
+    json(serde_json::json!(page))
+}
+
+/// Issue a token with a catalog, if the password credentials are valid
+fn authenticate(state: &mut State, url: &str, body: &[u8]) -> Response {
+    let body: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+    let user = &body["auth"]["identity"]["password"]["user"];
+    let project = &body["auth"]["scope"]["project"];
+    if user["name"] != "user"
+        || user["password"] != "password"
+        || user["domain"]["name"] != "Default"
+        || project["name"] != "project"
+    {
+        return status("401 Unauthorized");
+    }
+
+    state.tokens += 1;
+    state.expired = false;
+    let endpoint = |interface: &str, region: &str, path: &str| {
+        serde_json::json!({
+            "interface": interface,
+            "region_id": region,
+            "url": format!("{}{}", url, path),
+        })
+    };
+    let catalog = serde_json::json!({
+        "token": {
+            "catalog": [
+                {
+                    "type": "identity",
+                    "endpoints": [endpoint("public", "RegionTwo", "/v3")],
+                },
+                {
+                    "type": "object-store",
+                    "endpoints": [
+                        endpoint("public", "RegionOne", "/wrong"),
+                        endpoint("internal", "RegionTwo", "/wrong"),
+                        endpoint("public", "RegionTwo", "/v1/AUTH_test"),
+                    ],
+                },
+            ],
+        },
+    });
+    (
+        "201 Created",
+        format!("x-subject-token: token-{}\r\n", state.tokens),
+        catalog.to_string().into_bytes(),
+    )
+}
+
+/// Answer the request like Keystone and Swift
+fn handle(state: &mut State, url: &str, request: Request) -> Response {
+    let parsed = url::Url::parse(&format!("{}{}", url, request.path)).unwrap();
+    let query: HashMap<String, String> = parsed.query_pairs().into_owned().collect();
+    let path = parsed.path();
+
+    if path == "/v3/auth/tokens" && request.method == "POST" {
+        return authenticate(state, url, &request.body);
+    }
+    let token = request.headers.get("x-auth-token").cloned();
+    if state.expired || token != Some(format!("token-{}", state.tokens)) {
+        return status("401 Unauthorized");
+    }
+
+    // the slashes of object names are encoded by the client
+    let path = match path.strip_prefix("/v1/AUTH_test") {
+        Some(path) => path.trim_start_matches('/').to_string(),
+        None => return status("404 Not Found"),
+    };
+    let (container, name) = match path.split_once('/') {
+        Some((container, name)) => (container.to_string(), name.replace("%2F", "/")),
+        None => (path, String::new()),
+    };
+    let manifest = query.get("multipart-manifest").map(|m| m.as_str());
+
+    match (
+        request.method.as_str(),
+        container.is_empty(),
+        name.is_empty(),
+    ) {
+        ("GET", true, _) => page(state.containers.keys(), query.get("marker")),
+        ("PUT", false, true) => {
+            state.containers.entry(container).or_default();
+            status("201 Created")
+        }
+        ("DELETE", false, true) => match state.containers.get(&container) {
+            Some(objects) if objects.is_empty() => {
+                state.containers.remove(&container);
+                status("204 No Content")
+            }
+            Some(_) => status("409 Conflict"),
+            None => status("404 Not Found"),
+        },
+        ("GET", false, true) => match state.containers.get(&container) {
+            Some(objects) if objects.is_empty() => status("204 No Content"),
+            Some(objects) => page(objects.keys(), query.get("marker")),
+            None => status("404 Not Found"),
+        },
+        ("PUT", false, false) => {
+            let object = match manifest {
+                // the segments need to be uploaded with the given sizes before the manifest
+                Some("put") => {
+                    let segments: Vec<serde_json::Value> =
+                        serde_json::from_slice(&request.body).unwrap_or_default();
+                    let segments: Option<Vec<(String, usize)>> = segments
+                        .iter()
+                        .map(|s| {
+                            let path = s["path"].as_str()?.to_string();
+                            let (c, n) = path.trim_start_matches('/').split_once('/')?;
+                            let size = state.data(c, n)?.len();
+                            match s["size_bytes"].as_u64() == Some(size as u64) {
+                                true => Some((path.clone(), size)),
+                                false => None,
+                            }
+                        })
+                        .collect();
+                    match segments {
+                        Some(segments) if !segments.is_empty() => Object::Manifest(segments),
+                        _ => return status("400 Bad Request"),
+                    }
+                }
+                _ => Object::Data(request.body),
+            };
+            match state.containers.get_mut(&container) {
+                Some(objects) => {
+                    objects.insert(name, object);
+                    status("201 Created")
+                }
+                None => status("404 Not Found"),
+            }
+        }
+        ("HEAD", false, false) => {
+            match state.containers.get(&container).and_then(|o| o.get(&name)) {
+                Some(Object::Manifest(_)) => {
+                    ("200 OK", "x-static-large-object: True\r\n".into(), vec![])
+                }
+                Some(Object::Data(_)) => status("200 OK"),
+                None => status("404 Not Found"),
+            }
+        }
+        ("GET", false, false) => {
+            match state.containers.get(&container).and_then(|o| o.get(&name)) {
+                Some(Object::Manifest(segments)) if manifest == Some("get") => {
+                    json(serde_json::json!(segments
+                        .iter()
+                        .map(|(path, size)| serde_json::json!({ "name": path, "bytes": size }))
+                        .collect::<Vec<_>>()))
+                }
+                Some(_) => match state.data(&container, &name) {
+                    Some(data) => ("200 OK", String::new(), data),
+                    None => status("409 Conflict"),
+                },
+                None => status("404 Not Found"),
+            }
+        }
+        ("DELETE", false, false) => {
+            let object = match state
+                .containers
+                .get_mut(&container)
+                .and_then(|o| o.remove(&name))
+            {
+                Some(object) => object,
+                None => return status("404 Not Found"),
+            };
+            if let (Object::Manifest(segments), Some("delete")) = (object, manifest) {
+                for (path, _) in segments {
+                    let (c, n) = path.trim_start_matches('/').split_once('/').unwrap();
+                    if let Some(objects) = state.containers.get_mut(c) {
+                        objects.remove(n);
+                    }
+                }
+            }
+            status("204 No Content")
+        }
+        _ => status("405 Method Not Allowed"),
+    }
+}
+
+async fn read(swift: &stow::Swift, container: &str, item: &str) -> stow::Result<String> {
+    use stow::Adapter;
+
+    let mut buf = String::new();
+    swift
+        .read_item(container, item)
+        .await?
+        .read_to_string(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len());
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "throttle")]
+
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+
+#[tokio::test]
+async fn test_throttled_operations() -> stow::Result<()> {
+    let throttled = stow::Location::Throttled(
+        stow::Throttled::new(stow::Location::new_memory().await?)
+            .with_operations_per_second(20.0)?,
+    );
+    let clone = throttled.clone();
+    throttled.create_container("container-1").await?;
+
+    // one second of operations is available at once, the others wait
+    let start = Instant::now();
+    for _ in 0..19 {
+        throttled.items("container-1").await?;
+    }
+    assert!(start.elapsed() < Duration::from_millis(200));
+
+    // the budget is shared by the clones
+    for _ in 0..10 {
+        clone.items("container-1").await?;
+    }
+    assert!(start.elapsed() >= Duration::from_millis(400));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_throttled_bytes() -> stow::Result<()> {
+    let throttled =
+        stow::Location::new_throttled(stow::Location::new_memory().await?, 1000.0, 100_000).await?;
+    throttled.create_container("container-1").await?;
+
+    // 150 KB are written and read with 100 KB per second, after the first second of bytes
+    let data = vec![1; 150_000];
+    let start = Instant::now();
+    throttled
+        .create_item(
+            "container-1",
+            "test.bin",
+            std::io::Cursor::new(data.clone()),
+        )
+        .await?;
+
+    let mut buf = vec![];
+    throttled
+        .read_item("container-1", "test.bin")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(buf, data);
+    assert!(start.elapsed() >= Duration::from_millis(1500));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_throttled_invalid_rates() -> stow::Result<()> {
+    let invalid = |res: stow::Result<stow::Throttled>| {
+        matches!(res, Err(stow::StowError::InvalidRateError(_)))
+    };
+
+    // rates which aren't positive are refused, instead of dividing by them later
+    for operations in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let memory = stow::Location::new_memory().await?;
+        assert!(invalid(
+            stow::Throttled::new(memory).with_operations_per_second(operations)
+        ));
+    }
+    let memory = stow::Location::new_memory().await?;
+    assert!(invalid(
+        stow::Throttled::new(memory).with_bytes_per_second(0)
+    ));
+
+    let memory = stow::Location::new_memory().await?;
+    assert!(matches!(
+        stow::Location::new_throttled(memory, 10.0, 0).await,
+        Err(stow::StowError::InvalidRateError(_))
+    ));
+
+    Ok(())
+}
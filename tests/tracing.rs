@@ -0,0 +1,154 @@
+#![cfg(feature = "tracing")]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncReadExt;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing_core::span::Current;
+
+type Fields = HashMap<String, String>;
+
+/// Subscriber which keeps the fields of all spans
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<(&'static tracing::Metadata<'static>, Fields)>>>,
+    entered: Arc<Mutex<Vec<Id>>>,
+}
+
+impl Recorder {
+    /// Fields of the spans of the operation
+    fn spans(&self, operation: &str) -> Vec<Fields> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, fields)| fields)
+            .filter(|fields| fields.get("operation").map(|o| o.as_str()) == Some(operation))
+            .cloned()
+            .collect()
+    }
+}
+
+struct Visitor<'a>(&'a mut Fields);
+
+impl Visit for Visitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl tracing::Subscriber for Recorder {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields::new();
+        span.record(&mut Visitor(&mut fields));
+
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((span.metadata(), fields));
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut Visitor(&mut spans[span.into_u64() as usize - 1].1));
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &tracing::Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        self.entered.lock().unwrap().push(span.clone());
+    }
+
+    fn exit(&self, _: &Id) {
+        self.entered.lock().unwrap().pop();
+    }
+
+    fn current_span(&self) -> Current {
+        match self.entered.lock().unwrap().last() {
+            Some(id) => Current::new(
+                id.clone(),
+                self.spans.lock().unwrap()[id.into_u64() as usize - 1].0,
+            ),
+            None => Current::none(),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_tracing() -> stow::Result<()> {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let memory = stow::Location::new_memory().await?;
+    memory.create_container("container-1").await?;
+    memory
+        .create_item(
+            "container-1",
+            "test.txt",
+            std::io::Cursor::new(b"Hello World"),
+        )
+        .await?;
+
+    let mut buf = vec![];
+    let mut reader = memory.read_item("container-1", "test.txt").await?;
+    reader.read_to_end(&mut buf).await?;
+    drop(reader);
+    assert!(memory
+        .read_item("container-1", "missing.txt")
+        .await
+        .is_err());
+
+    // every operation has a span with the location, the outcome and the transferred bytes
+    let spans = recorder.spans("create_item");
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0]["backend"], "memory");
+    assert_eq!(spans[0]["storage_backend"], "memory");
+    assert_eq!(spans[0]["container"], "container-1");
+    assert_eq!(spans[0]["item"], "test.txt");
+    assert_eq!(spans[0]["bytes"], "11");
+    assert_eq!(spans[0]["outcome"], "ok");
+
+    let spans = recorder.spans("read_item");
+    assert_eq!(spans.len(), 2);
+    assert_eq!(spans[0]["bytes"], "11");
+    assert_eq!(spans[0]["outcome"], "ok");
+    assert_eq!(spans[1]["item"], "missing.txt");
+    assert_eq!(spans[1]["outcome"], "error");
+    assert!(spans[1].contains_key("error"));
+
+    assert_eq!(recorder.spans("create_container")[0]["outcome"], "ok");
+
+    Ok(())
+}
+
+#[cfg(all(feature = "retry", feature = "guard"))]
+#[tokio::test]
+async fn test_tracing_wrapped() -> stow::Result<()> {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let memory = stow::Location::new_memory().await?;
+    let retry = stow::Location::new_retry(memory).await?;
+    let guarded = stow::Location::new_guarded(retry, stow::GuardPolicy::default()).await?;
+    assert_eq!(guarded.storage_backend(), "memory");
+    guarded.create_container("container-1").await?;
+
+    // every wrapper has its own span, which records the backend storing the data as well
+    let spans = recorder.spans("create_container");
+    let backends: Vec<_> = spans.iter().map(|s| s["backend"].as_str()).collect();
+    assert_eq!(backends, vec!["guarded", "retry", "memory"]);
+    assert!(spans.iter().all(|s| s["storage_backend"] == "memory"));
+
+    Ok(())
+}
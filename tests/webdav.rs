@@ -0,0 +1,402 @@
+#![cfg(feature = "webdav")]
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_webdav() -> stow::Result<()> {
+    if let Err(e) = dotenv::dotenv() {
+        if !e.not_found() {
+            let e: Result<(), dotenv::Error> = Err(e);
+            e.unwrap();
+        }
+    }
+
+    // skipped without a WebDAV server, e.g. in CI
+    if std::env::var("STOW_WEBDAV_URL").is_err() {
+        eprintln!("STOW_WEBDAV_URL is not set, skipping test_webdav");
+        return Ok(());
+    }
+
+    let url = std::env::var("STOW_WEBDAV_URL")?;
+    let container_1 = std::env::var("STOW_TEST_CONTAINER_1")?;
+    let container_2 = std::env::var("STOW_TEST_CONTAINER_2")?;
+
+    // create a new environment if not avilable
+    // authenticate with a token or user and password if given
+    let auth = match (
+        std::env::var("STOW_WEBDAV_TOKEN"),
+        std::env::var("STOW_WEBDAV_USER"),
+    ) {
+        (Ok(token), _) => stow::WebDavAuth::Bearer(token),
+        (_, Ok(user)) => stow::WebDavAuth::Basic {
+            user,
+            password: std::env::var("STOW_WEBDAV_PASSWORD")?,
+        },
+        _ => stow::WebDavAuth::None,
+    };
+    let webdav = stow::Location::new_webdav(&url, auth).await?;
+
+    // create new containers if not avilable
+    webdav.create_container(&container_1).await?;
+    webdav.create_container(&container_2).await?;
+
+    assert!(webdav
+        .containers()
+        .await?
+        .contains(&String::from(&container_1)));
+    assert!(webdav
+        .containers()
+        .await?
+        .contains(&String::from(&container_2)));
+
+    // create two test.txt file
+    webdav
+        .create_item(&container_1, "test.txt", reader("Hello World 1").await?)
+        .await?;
+    webdav
+        .create_item(&container_2, "test.txt", reader("Hello World 2").await?)
+        .await?;
+
+    assert!(webdav
+        .items(&container_2)
+        .await?
+        .contains(&String::from("test.txt")));
+
+    // rewrite the test.txt file
+    webdav
+        .create_item(&container_1, "test.txt", reader("Hello World 1 New").await?)
+        .await?;
+
+    // read the test.txt file
+    let mut buf = vec![];
+    webdav
+        .read_item(&container_1, "test.txt")
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    assert_eq!(&b"Hello World 1 New"[0..], &buf);
+
+    // remove the item.txt in container 2
+    webdav.remove_item(&container_2, "test.txt").await?;
+    assert!(webdav.read_item(&container_2, "test.txt").await.is_err());
+    // remove the container 2
+    webdav.remove_container(&container_2).await?;
+
+    // remove the item.txt in container 1
+    webdav.remove_item(&container_1, "test.txt").await?;
+    assert!(webdav.read_item(&container_1, "test.txt").await.is_err());
+    webdav.remove_container(&container_1).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_webdav_mock() -> stow::Result<()> {
+    use stow::Adapter;
+
+    let (url, state) = mock_server().await?;
+    let webdav = stow::WebDav::new(
+        &format!("{}/dav/files/user", url),
+        stow::WebDavAuth::Bearer("secret".to_string()),
+    )
+    .await?;
+    assert!(webdav.containers().await?.is_empty());
+
+    // creating an available collection again is fine
+    webdav.create_container("container-1").await?;
+    webdav.create_container("container-2").await?;
+    webdav.create_container("container-1").await?;
+
+    // files next to the containers and collections next to the items are not listed
+    {
+        let mut state = state.lock().unwrap();
+        let base = "/dav/files/user";
+        state.insert(&format!("{}/readme.txt", base), Some(b"Hello".to_vec()));
+        state.insert(&format!("{}/container-1/sub", base), None);
+        state.insert(&format!("{}/container-1/my file.txt", base), Some(vec![]));
+    }
+    assert_eq!(
+        webdav.containers().await?,
+        vec!["container-1", "container-2"]
+    );
+
+    webdav
+        .create_item("container-1", "test.txt", reader("Hello World 1").await?)
+        .await?;
+    webdav
+        .create_item("container-2", "test.txt", reader("Hello World 2").await?)
+        .await?;
+    assert_eq!(
+        webdav.items("container-1").await?,
+        vec!["my file.txt", "test.txt"]
+    );
+    assert_eq!(webdav.items("container-2").await?, vec!["test.txt"]);
+
+    webdav
+        .create_item(
+            "container-1",
+            "test.txt",
+            reader("Hello World 1 New").await?,
+        )
+        .await?;
+    assert_eq!(
+        read(&webdav, "container-1", "test.txt").await?,
+        "Hello World 1 New"
+    );
+
+    webdav.remove_item("container-2", "test.txt").await?;
+    assert!(webdav.read_item("container-2", "test.txt").await.is_err());
+    assert!(webdav.remove_item("container-2", "test.txt").await.is_err());
+
+    // collections are removed with their content
+    webdav.remove_container("container-1").await?;
+    webdav.remove_container("container-2").await?;
+    assert!(webdav.containers().await?.is_empty());
+    assert!(webdav.items("container-1").await.is_err());
+
+    // a wrong token is refused
+    let other = stow::WebDav::new(
+        &format!("{}/dav/files/user", url),
+        stow::WebDavAuth::Bearer("other".to_string()),
+    )
+    .await?;
+    assert!(other.containers().await.is_err());
+
+    Ok(())
+}
+
+/// Files and collections of the mock server by their path, collections have no data
+struct State {
+    nodes: BTreeMap<String, Option<Vec<u8>>>,
+}
+
+impl State {
+    fn insert(&mut self, path: &str, data: Option<Vec<u8>>) {
+        self.nodes.insert(path.to_string(), data);
+    }
+
+    /// Check that the parent of the path is a collection
+    fn has_parent(&self, path: &str) -> bool {
+        let parent = path.rsplit_once('/').map(|(p, _)| p).unwrap_or_default();
+        matches!(self.nodes.get(parent), Some(None))
+    }
+}
+
+/// Serve a WebDAV server with the collection `/dav/files/user` on a local port
+async fn mock_server() -> stow::Result<(String, Arc<Mutex<State>>)> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let mut nodes = BTreeMap::new();
+    nodes.insert("/dav/files/user".to_string(), None);
+    let state = Arc::new(Mutex::new(State { nodes }));
+
+    let (u, s) = (url.clone(), state.clone());
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let (status, body) = match read_request(&mut socket).await {
+                Some(request) => handle(&mut s.lock().unwrap(), &u, request),
+                None => continue,
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+            let _ = socket.write_all(&body).await;
+        }
+    });
+
+    Ok((url, state))
+}
+
+/// Method, path, lowercase headers and body of a request
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// Read from the socket, until the buffer holds at least the given number of bytes
+async fn fill(socket: &mut tokio::net::TcpStream, buf: &mut Vec<u8>, len: usize) -> Option<()> {
+    while buf.len() < len {
+        let mut chunk = [0; 4096];
+        match socket.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+    Some(())
+}
+
+async fn read_request(socket: &mut tokio::net::TcpStream) -> Option<Request> {
+    let mut buf = vec![];
+    let end = loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+        let len = buf.len() + 1;
+        fill(socket, &mut buf, len).await?;
+    };
+
+    let head = String::from_utf8_lossy(&buf[..end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(n, v)| (n.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let mut rest = buf.split_off(end + 4);
+    let body = match headers.get("transfer-encoding").map(|t| t.as_str()) {
+        // streamed uploads are sent in chunks, which start with their hex size
+        Some("chunked") => {
+            let mut body = vec![];
+            loop {
+                let line = loop {
+                    if let Some(line) = rest.windows(2).position(|w| w == b"\r\n") {
+                        break line;
+                    }
+                    let len = rest.len() + 1;
+                    fill(socket, &mut rest, len).await?;
+                };
+                let size = String::from_utf8_lossy(&rest[..line]).to_string();
+                let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+                fill(socket, &mut rest, line + size + 4).await?;
+                body.extend_from_slice(&rest[line + 2..line + 2 + size]);
+                rest.drain(..line + size + 4);
+                if size == 0 {
+                    break body;
+                }
+            }
+        }
+        _ => {
+            let length: usize = headers
+                .get("content-length")
+                .and_then(|l| l.parse().ok())
+                .unwrap_or(0);
+            fill(socket, &mut rest, length).await?;
+            rest
+        }
+    };
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+/// Entry of a multistatus response, collections get a trailing slash
+fn response(href: &str, collection: bool) -> String {
+    // names are percent encoded in the href
+    let href = href.replace(' ', "%20");
+    let (slash, resourcetype) = match collection {
+        true => ("/", "<D:resourcetype><D:collection/></D:resourcetype>"),
+        false => ("", "<D:resourcetype/>"),
+    };
+    format!(
+        "<D:response><D:href>{}{}</D:href><D:propstat><D:prop>{}</D:prop>\
+         <D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+        href, slash, resourcetype
+    )
+}
+
+/// Answer the request like a WebDAV server
+fn handle(state: &mut State, url: &str, request: Request) -> (&'static str, Vec<u8>) {
+    if request.headers.get("authorization").map(|a| a.as_str()) != Some("Bearer secret") {
+        return ("401 Unauthorized", vec![]);
+    }
+
+    let path = percent_encoding::percent_decode_str(&request.path)
+        .decode_utf8_lossy()
+        .trim_end_matches('/')
+        .to_string();
+
+    match request.method.as_str() {
+        "PROPFIND" => {
+            // only the direct children are listed, like most servers allow it
+            if request.headers.get("depth").map(|d| d.as_str()) != Some("1") {
+                return ("403 Forbidden", vec![]);
+            }
+            if !matches!(state.nodes.get(&path), Some(None)) {
+                return ("404 Not Found", vec![]);
+            }
+
+            let prefix = format!("{}/", path);
+            let mut xml = String::from(
+                r#"<?xml version="1.0" encoding="utf-8"?><D:multistatus xmlns:D="DAV:">"#,
+            );
+            xml.push_str(&response(&path, true));
+            let children = state
+                .nodes
+                .iter()
+                .filter(|(p, _)| p.starts_with(&prefix) && !p[prefix.len()..].contains('/'));
+            for (i, (child, data)) in children.enumerate() {
+                // some servers answer with absolute urls instead of paths
+                let href = match i % 2 {
+                    0 => child.clone(),
+                    _ => format!("{}{}", url, child),
+                };
+                xml.push_str(&response(&href, data.is_none()));
+            }
+            xml.push_str("</D:multistatus>");
+            ("207 Multi-Status", xml.into_bytes())
+        }
+        "MKCOL" => {
+            if state.nodes.contains_key(&path) {
+                return ("405 Method Not Allowed", vec![]);
+            }
+            if !state.has_parent(&path) {
+                return ("409 Conflict", vec![]);
+            }
+            state.insert(&path, None);
+            ("201 Created", vec![])
+        }
+        "PUT" => {
+            if !state.has_parent(&path) || matches!(state.nodes.get(&path), Some(None)) {
+                return ("409 Conflict", vec![]);
+            }
+            state.insert(&path, Some(request.body));
+            ("201 Created", vec![])
+        }
+        "GET" => match state.nodes.get(&path) {
+            Some(Some(data)) => ("200 OK", data.clone()),
+            _ => ("404 Not Found", vec![]),
+        },
+        "DELETE" => {
+            if state.nodes.remove(&path).is_none() {
+                return ("404 Not Found", vec![]);
+            }
+            let prefix = format!("{}/", path);
+            state.nodes.retain(|p, _| !p.starts_with(&prefix));
+            ("204 No Content", vec![])
+        }
+        _ => ("405 Method Not Allowed", vec![]),
+    }
+}
+
+async fn read(webdav: &stow::WebDav, container: &str, item: &str) -> stow::Result<String> {
+    use stow::Adapter;
+
+    let mut buf = String::new();
+    webdav
+        .read_item(container, item)
+        .await?
+        .read_to_string(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+async fn reader(data: &str) -> stow::Result<tokio::io::DuplexStream> {
+    let (mut send, recv) = tokio::io::duplex(data.len());
+    send.write_all(data.as_bytes()).await?;
+    send.shutdown().await?;
+    Ok(recv)
+}